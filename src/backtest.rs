@@ -0,0 +1,199 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, RatingModel, SrsConfig};
+use crate::types::{Game, RatingChange, Team};
+use crate::utils::{self, Paths};
+use crate::{bradley_terry, display, point_ratings, process_data};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BacktestResult {
+    pub rating_model: RatingModel,
+    pub seasons: Vec<Metrics>,
+    pub overall: Metrics,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Metrics {
+    pub season: Option<u32>,
+    pub games: u32,
+    pub brier: f64,
+    pub log_loss: f64,
+    pub accuracy: f64,
+    pub calibration: Vec<Bucket>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Bucket {
+    pub from: f64,
+    pub to: f64,
+    pub games: u32,
+    pub expected: f64,
+    pub actual: f64,
+}
+
+impl Metrics {
+    fn new(season: Option<u32>, changes: &[&RatingChange], buckets: u32) -> Self {
+        let width = 1.0 / f64::from(buckets);
+        let mut calibration: Vec<_> = (0..buckets)
+            .map(|i| Bucket {
+                from: f64::from(i) * width,
+                to: f64::from(i + 1) * width,
+                ..Bucket::default()
+            })
+            .collect();
+        let last = calibration.len() - 1;
+        let (mut brier, mut log_loss, mut correct) = (0.0, 0.0, 0);
+        for change in changes {
+            let actual = if change.home_win { 1.0 } else { 0.0 };
+            let probability = if change.home_win { change.expected } else { 1.0 - change.expected };
+            brier += (change.expected - actual).powi(2);
+            log_loss -= probability.max(f64::MIN_POSITIVE).ln();
+            correct += u32::from((change.expected >= 0.5) == change.home_win);
+            let i = calibration.iter().position(|x| change.expected < x.to).unwrap_or(last);
+            let bucket = &mut calibration[i];
+            bucket.games += 1;
+            bucket.expected += change.expected;
+            bucket.actual += actual;
+        }
+        for bucket in &mut calibration {
+            if bucket.games > 0 {
+                bucket.expected /= f64::from(bucket.games);
+                bucket.actual /= f64::from(bucket.games);
+            }
+        }
+        let games = u32::try_from(changes.len()).unwrap_or(u32::MAX);
+        let count = f64::from(games.max(1));
+        Metrics {
+            season,
+            games,
+            brier: brier / count,
+            log_loss: log_loss / count,
+            accuracy: f64::from(correct) / count,
+            calibration,
+        }
+    }
+}
+
+fn refit(teams: &mut [Team], games: &[Game], config: &mut Config) -> Result<()> {
+    if config.fit_home_advantage {
+        config.fit_home_advantage(games);
+    }
+    if config.rating_model == RatingModel::BradleyTerry {
+        bradley_terry::fit(teams, games, config);
+        Ok(())
+    } else {
+        point_ratings::fit(teams, &point_ratings::latest_season(games), config)
+    }
+}
+
+fn replay_seasons(teams: Vec<Team>, games: &[Game], config: &mut Config) -> Vec<RatingChange> {
+    let teams: Vec<_> = teams.into_iter().map(RefCell::new).collect();
+    let mut history = Vec::new();
+    let refit_home_advantage = |config: &mut Config, i: usize| {
+        if config.fit_home_advantage {
+            config.fit_home_advantage(&games[..i]);
+        }
+    };
+    process_data::replay(games, &teams, config, "", refit_home_advantage, |x| history.push(x));
+    history
+}
+
+/// Predicts every game using only the games played before it.
+fn walk_forward(paths: &Paths, refit_days: u32) -> Result<(Vec<RatingChange>, Config)> {
+    let mut teams = utils::load_teams(&paths.teams)?;
+    let mut games =
+        process_data::valid_games(utils::load_games(&paths.previous_games, &teams)?, &teams);
+    games.extend(process_data::load_current_games(&paths.games, &teams)?);
+    games.sort_by_key(|x| x.date);
+    let mut config = Config::load(&paths.config)?;
+    if config.fit_home_advantage {
+        // the saved values were fitted on every game, start over and fit them on earlier ones
+        config.home_advantage = Config::default().home_advantage;
+        config.srs.home_points = SrsConfig::default().home_points;
+    }
+    process_data::reset_ratings(&mut teams, &config);
+    if matches!(config.rating_model, RatingModel::Elo | RatingModel::Glicko) {
+        let history = replay_seasons(teams, &games, &mut config);
+        return Ok((history, config));
+    }
+    let predictor = config.predictor();
+    let mut history = Vec::new();
+    let mut fitted_at = None;
+    for (i, game) in games.iter().enumerate() {
+        if fitted_at.is_none_or(|x| utils::days(x, game.date) >= f64::from(refit_days)) {
+            refit(&mut teams, &games[..i], &mut config)?;
+            fitted_at = Some(game.date);
+        }
+        let (home, away) = (&teams[game.home_team_idx], &teams[game.away_team_idx]);
+        let expected = predictor.predict(&config, home, away, game.neutral, Some(game.date));
+        let home_win = game.home_score > game.away_score;
+        history.push(RatingChange::new(game, home, away, home_win, expected, home.elo, away.elo));
+    }
+    Ok((history, config))
+}
+
+pub fn backtest(paths: &Paths, buckets: u32, refit_days: u32) -> Result<()> {
+    let (history, config) = walk_forward(paths, refit_days)?;
+    let mut by_season: BTreeMap<u32, Vec<&RatingChange>> = BTreeMap::new();
+    for change in &history {
+        by_season.entry(change.season).or_default().push(change);
+    }
+    let result = BacktestResult {
+        rating_model: config.rating_model,
+        seasons: by_season
+            .into_iter()
+            .map(|(season, changes)| Metrics::new(Some(season), &changes, buckets))
+            .collect(),
+        overall: Metrics::new(None, &history.iter().collect::<Vec<_>>(), buckets),
+    };
+    utils::write_json(&result, &paths.backtest)?;
+    display::display_backtest(&result);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn change(expected: f64, home_win: bool) -> RatingChange {
+        RatingChange {
+            date: Utc::now(),
+            season: 2023,
+            game_id: 0,
+            home: "BOS".into(),
+            away: "MIA".into(),
+            home_score: 0,
+            away_score: 0,
+            home_win,
+            simulated: false,
+            expected,
+            home_before: 1000.0,
+            home_after: 1000.0,
+            away_before: 1000.0,
+            away_after: 1000.0,
+        }
+    }
+
+    #[test]
+    fn computes_scores_and_calibration() {
+        let changes = [change(0.5, true), change(1.0, true), change(0.2, true), change(0.8, false)];
+        let metrics = Metrics::new(None, &changes.iter().collect::<Vec<_>>(), 4);
+        assert_eq!(metrics.games, 4);
+        let brier = (0.25 + 0.0 + 0.64 + 0.64) / 4.0;
+        assert!((metrics.brier - brier).abs() < 1e-9, "{}", metrics.brier);
+        let log_loss = -(0.5f64.ln() + 1.0f64.ln() + 0.2f64.ln() + 0.2f64.ln()) / 4.0;
+        assert!((metrics.log_loss - log_loss).abs() < 1e-9, "{}", metrics.log_loss);
+        assert!((metrics.accuracy - 0.5).abs() < 1e-9, "{}", metrics.accuracy);
+        // 0.5 opens the third bucket and 1.0 falls into the last one
+        let games: Vec<_> = metrics.calibration.iter().map(|x| x.games).collect();
+        assert_eq!(games, [1, 0, 1, 2]);
+        let last = &metrics.calibration[3];
+        assert!((last.expected - 0.9).abs() < 1e-9 && (last.actual - 0.5).abs() < 1e-9);
+    }
+}
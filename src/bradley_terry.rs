@@ -0,0 +1,134 @@
+use crate::config::Config;
+use crate::predictor::SCALE;
+use crate::types::{Game, Team};
+use crate::utils;
+
+const MAX_ITERATIONS: u32 = 1000;
+const TOLERANCE: f64 = 0.000_000_1;
+
+struct Model<'a> {
+    games: &'a [Game],
+    weights: Vec<f64>,
+    ratings: Vec<f64>,
+    home_advantage: f64,
+}
+
+impl Model<'_> {
+    fn probability(&self, game: &Game) -> f64 {
+        let home = if game.neutral { 0.0 } else { self.home_advantage };
+        let diff = self.ratings[game.home_team_idx] + home - self.ratings[game.away_team_idx];
+        1.0 / (1.0 + (-diff).exp())
+    }
+
+    fn step<F>(&self, games: &[usize], regularization: f64, value: f64, sign: F) -> f64
+    where F: Fn(&Game) -> f64 {
+        let mut gradient = -regularization * value;
+        let mut curvature = regularization;
+        for &i in games {
+            let game = &self.games[i];
+            let probability = self.probability(game);
+            let actual = if game.home_score > game.away_score { 1.0 } else { 0.0 };
+            gradient += sign(game) * self.weights[i] * (actual - probability);
+            curvature += self.weights[i] * probability * (1.0 - probability);
+        }
+        gradient / curvature
+    }
+}
+
+pub fn fit(teams: &mut [Team], games: &[Game], config: &mut Config) {
+    let latest = games.iter().map(|x| x.date).max();
+    let weights = games
+        .iter()
+        .map(|x| match (config.bradley_terry.half_life, latest) {
+            (Some(half_life), Some(latest)) => 0.5f64.powf(utils::days(x.date, latest) / half_life),
+            _ => 1.0,
+        })
+        .collect();
+    let mut by_team = vec![Vec::new(); teams.len()];
+    for (i, game) in games.iter().enumerate() {
+        by_team[game.home_team_idx].push(i);
+        by_team[game.away_team_idx].push(i);
+    }
+    let home_games: Vec<_> = (0..games.len()).filter(|&i| !games[i].neutral).collect();
+    let mut model = Model {
+        games,
+        weights,
+        ratings: vec![0.0; teams.len()],
+        home_advantage: config.home_advantage / SCALE,
+    };
+    let regularization = config.bradley_terry.regularization;
+    for _ in 0..MAX_ITERATIONS {
+        let mut largest: f64 = 0.0;
+        for (team, games) in by_team.iter().enumerate() {
+            if games.is_empty() {
+                continue;
+            }
+            let sign = |game: &Game| if game.home_team_idx == team { 1.0 } else { -1.0 };
+            let step = model.step(games, regularization, model.ratings[team], sign);
+            model.ratings[team] += step;
+            largest = largest.max(step.abs());
+        }
+        if config.fit_home_advantage && !home_games.is_empty() {
+            let step = model.step(&home_games, 0.0, model.home_advantage, |_| 1.0);
+            model.home_advantage += step;
+            largest = largest.max(step.abs());
+        }
+        if largest < TOLERANCE {
+            break;
+        }
+    }
+    let (total, count) =
+        model.ratings.iter().fold((0.0, 0.0), |(total, count), x| (total + x, count + 1.0));
+    let mean = total / count;
+    for (team, rating) in teams.iter_mut().zip(&model.ratings) {
+        team.elo = config.initial_elo + (rating - mean) * SCALE;
+        team.glicko = None;
+    }
+    config.home_advantage = model.home_advantage * SCALE;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{game, team};
+
+    fn result(home: usize, away: usize, home_win: bool, neutral: bool) -> Game {
+        let (home_score, away_score) = if home_win { (110, 100) } else { (100, 110) };
+        Game { neutral, ..game(home, away, home_score, away_score) }
+    }
+
+    fn config(fit_home_advantage: bool) -> Config {
+        let mut config = Config { fit_home_advantage, ..Config::default() };
+        config.bradley_terry.regularization = 0.0;
+        config
+    }
+
+    #[test]
+    fn fits_home_advantage_between_equal_teams() {
+        // both teams win three of four at home, so only home court separates them
+        let games: Vec<_> = [true, true, true, false]
+            .into_iter()
+            .flat_map(|x| [result(0, 1, x, false), result(1, 0, x, false)])
+            .collect();
+        let mut teams = vec![team("BOS"), team("MIA")];
+        let mut config = config(true);
+        fit(&mut teams, &games, &mut config);
+        assert!((teams[0].elo - teams[1].elo).abs() < 0.001, "{} {}", teams[0].elo, teams[1].elo);
+        let expected = 3.0f64.ln() * SCALE;
+        assert!((config.home_advantage - expected).abs() < 0.001, "{}", config.home_advantage);
+    }
+
+    #[test]
+    fn ranks_teams_and_ignores_teams_without_games() {
+        // every pairing goes two to one in favour of the lower index
+        let games: Vec<_> = [(0, 1), (1, 2), (0, 2)]
+            .into_iter()
+            .flat_map(|(x, y)| [true, true, false].map(|z| result(x, y, z, true)))
+            .collect();
+        let mut teams = vec![team("BOS"), team("MIA"), team("NYK"), team("PHI")];
+        let mut config = config(false);
+        fit(&mut teams, &games, &mut config);
+        assert!(teams.iter().all(|x| x.elo.is_finite()));
+        assert!(teams[0].elo > teams[1].elo && teams[1].elo > teams[2].elo);
+    }
+}
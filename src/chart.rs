@@ -0,0 +1,184 @@
+use std::fmt::{self, Write};
+use std::fs;
+
+use anyhow::{anyhow, Result};
+use chrono::Datelike;
+
+use crate::types::RatingChange;
+
+const WIDTH: f64 = 1200.0;
+const HEIGHT: f64 = 600.0;
+const LEFT: f64 = 60.0;
+const RIGHT: f64 = 110.0;
+const TOP: f64 = 30.0;
+const BOTTOM: f64 = 40.0;
+const STEP: f64 = 50.0;
+const COLORS: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+struct Series {
+    name: String,
+    points: Vec<(f64, f64)>,
+}
+
+fn day(change: &RatingChange) -> f64 { f64::from(change.date.num_days_from_ce()) }
+
+fn series(history: &[RatingChange], names: &[String]) -> Vec<Series> {
+    let mut result: Vec<Series> =
+        names.iter().map(|x| Series { name: x.clone(), points: Vec::new() }).collect();
+    for change in history {
+        for series in &mut result {
+            let (before, after) = if change.home == series.name {
+                (change.home_before, change.home_after)
+            } else if change.away == series.name {
+                (change.away_before, change.away_after)
+            } else {
+                continue;
+            };
+            if series.points.is_empty() {
+                series.points.push((day(change), before));
+            }
+            series.points.push((day(change), after));
+        }
+    }
+    result
+}
+
+fn season_starts(history: &[RatingChange]) -> Vec<(f64, u32)> {
+    let mut result: Vec<(f64, u32)> = Vec::new();
+    for change in history {
+        if result.last().is_none_or(|x| x.1 != change.season) {
+            result.push((day(change), change.season));
+        }
+    }
+    result
+}
+
+struct Frame {
+    x_min: f64,
+    x_range: f64,
+    y_min: f64,
+    y_max: f64,
+}
+
+impl Frame {
+    fn new(series: &[Series]) -> Self {
+        let points = series.iter().flat_map(|x| &x.points);
+        let (x_min, x_max, y_min, y_max) = points.fold(
+            (f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY, f64::NEG_INFINITY),
+            |(x_min, x_max, y_min, y_max), &(x, y)| {
+                (x_min.min(x), x_max.max(x), y_min.min(y), y_max.max(y))
+            },
+        );
+        let y_min = (y_min / STEP).floor() * STEP;
+        let y_max = ((y_max / STEP).ceil() * STEP).max(y_min + STEP);
+        Frame { x_min, x_range: (x_max - x_min).max(1.0), y_min, y_max }
+    }
+
+    fn x(&self, x: f64) -> f64 { LEFT + (x - self.x_min) / self.x_range * (WIDTH - LEFT - RIGHT) }
+
+    fn y(&self, y: f64) -> f64 {
+        TOP + (self.y_max - y) / (self.y_max - self.y_min) * (HEIGHT - TOP - BOTTOM)
+    }
+}
+
+fn draw_grid(svg: &mut String, frame: &Frame, history: &[RatingChange]) -> fmt::Result {
+    let mut y = frame.y_min;
+    while y <= frame.y_max {
+        let sy = frame.y(y);
+        writeln!(
+            svg,
+            r##"<line x1="{LEFT}" y1="{sy:.1}" x2="{:.1}" y2="{sy:.1}" stroke="#e0e0e0"/>"##,
+            WIDTH - RIGHT
+        )?;
+        writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{y}</text>"#,
+            LEFT - 5.0,
+            sy + 4.0
+        )?;
+        y += STEP;
+    }
+    for (x, season) in season_starts(history) {
+        let sx = frame.x(x);
+        writeln!(
+            svg,
+            r##"<line x1="{sx:.1}" y1="{TOP}" x2="{sx:.1}" y2="{:.1}" stroke="#999" stroke-dasharray="4 4"/>"##,
+            HEIGHT - BOTTOM
+        )?;
+        writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}">{season}-{:02}</text>"#,
+            sx + 4.0,
+            HEIGHT - BOTTOM + 16.0,
+            (season + 1) % 100
+        )?;
+    }
+    writeln!(
+        svg,
+        r#"<rect x="{LEFT}" y="{TOP}" width="{}" height="{}" fill="none" stroke="black"/>"#,
+        WIDTH - LEFT - RIGHT,
+        HEIGHT - TOP - BOTTOM
+    )
+}
+
+fn draw_series(svg: &mut String, frame: &Frame, series: &Series, i: u32) -> fmt::Result {
+    let color = COLORS[i as usize % COLORS.len()];
+    let points: Vec<_> = series
+        .points
+        .iter()
+        .map(|&(x, y)| format!("{:.1},{:.1}", frame.x(x), frame.y(y)))
+        .collect();
+    writeln!(
+        svg,
+        r#"<polyline fill="none" stroke="{color}" stroke-width="1.5" points="{}"/>"#,
+        points.join(" ")
+    )?;
+    let legend_x = WIDTH - RIGHT + 10.0;
+    let legend_y = TOP + 10.0 + 16.0 * f64::from(i);
+    writeln!(
+        svg,
+        r#"<line x1="{legend_x}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{color}" stroke-width="3"/>"#,
+        legend_y - 4.0,
+        legend_x + 20.0,
+        legend_y - 4.0
+    )?;
+    writeln!(svg, r#"<text x="{:.1}" y="{legend_y:.1}">{}</text>"#, legend_x + 25.0, series.name)
+}
+
+pub fn elo_chart(history: &[RatingChange], teams: &[String], path: &str) -> Result<()> {
+    if history.is_empty() {
+        return Err(anyhow!("rating history is empty, nothing to chart"));
+    }
+    let mut names: Vec<String> = teams.iter().map(|x| x.to_uppercase()).collect();
+    if names.is_empty() {
+        for change in history {
+            for name in [&change.home, &change.away] {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        names.sort();
+    }
+    let series = series(history, &names);
+    if let Some(missing) = series.iter().find(|x| x.points.is_empty()) {
+        return Err(anyhow!("no rating history for team {}", missing.name));
+    }
+    let frame = Frame::new(&series);
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" font-family="sans-serif" font-size="12">"#
+    )?;
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+    draw_grid(&mut svg, &frame, history)?;
+    for (i, series) in (0..).zip(&series) {
+        draw_series(&mut svg, &frame, series, i)?;
+    }
+    writeln!(svg, "</svg>")?;
+    fs::write(path, svg)?;
+    Ok(())
+}
@@ -0,0 +1,237 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::predictor::{BradleyTerry, Elo, Glicko, Predictor, Pythagorean, Srs};
+use crate::types::Game;
+use crate::utils;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub k_factor: f64,
+    pub initial_elo: f64,
+    pub home_advantage: f64,
+    pub fit_home_advantage: bool,
+    pub margin_of_victory: bool,
+    pub season_regression: f64,
+    pub rating_model: RatingModel,
+    pub glicko: GlickoConfig,
+    pub bradley_terry: BradleyTerryConfig,
+    pub srs: SrsConfig,
+    pub playoff_format: PlayoffFormat,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            k_factor: 32.0,
+            initial_elo: 1000.0,
+            home_advantage: 100.0,
+            fit_home_advantage: true,
+            margin_of_victory: false,
+            season_regression: 0.25,
+            rating_model: RatingModel::Elo,
+            glicko: GlickoConfig::default(),
+            bradley_terry: BradleyTerryConfig::default(),
+            srs: SrsConfig::default(),
+            playoff_format: PlayoffFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RatingModel {
+    Elo,
+    Glicko,
+    BradleyTerry,
+    Srs,
+    Pythagorean,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlickoConfig {
+    pub initial_rd: f64,
+    pub initial_volatility: f64,
+    pub tau: f64,
+}
+
+impl Default for GlickoConfig {
+    fn default() -> Self { GlickoConfig { initial_rd: 350.0, initial_volatility: 0.06, tau: 0.5 } }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BradleyTerryConfig {
+    pub half_life: Option<f64>,
+    pub regularization: f64,
+}
+
+impl Default for BradleyTerryConfig {
+    fn default() -> Self { BradleyTerryConfig { half_life: None, regularization: 0.01 } }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SrsConfig {
+    pub home_points: f64,
+    pub average_points: f64,
+    pub margin_deviation: f64,
+    pub total_deviation: f64,
+    pub pythagorean_exponent: f64,
+}
+
+impl Default for SrsConfig {
+    fn default() -> Self {
+        SrsConfig {
+            home_points: 2.5,
+            average_points: 112.0,
+            margin_deviation: 12.0,
+            total_deviation: 18.0,
+            pythagorean_exponent: 14.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayoffFormat {
+    pub round_1: SeriesFormat,
+    pub semifinals: SeriesFormat,
+    pub conference_finals: SeriesFormat,
+    pub finals: SeriesFormat,
+}
+
+impl Default for PlayoffFormat {
+    fn default() -> Self {
+        let format = SeriesFormat { blocks: vec![2, 2, 1, 1, 1] };
+        PlayoffFormat {
+            round_1: format.clone(),
+            semifinals: format.clone(),
+            conference_finals: format.clone(),
+            finals: format,
+        }
+    }
+}
+
+// the playoff ladder prints the wins of each team as a single digit
+const MAX_SERIES_GAMES: u32 = 17;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SeriesFormat {
+    blocks: Vec<u32>,
+}
+
+impl SeriesFormat {
+    #[must_use]
+    pub fn wins_needed(&self) -> u32 { self.blocks.iter().sum::<u32>() / 2 + 1 }
+
+    #[must_use]
+    pub fn higher_seed_home(&self, game: u32) -> bool {
+        let mut played = 0;
+        for (i, block) in self.blocks.iter().enumerate() {
+            played += block;
+            if game <= played {
+                return i % 2 == 0;
+            }
+        }
+        false
+    }
+}
+
+impl TryFrom<String> for SeriesFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        let blocks = value.split('-').map(|x| x.trim().parse()).collect::<Result<Vec<u32>, _>>()?;
+        let games = blocks.iter().sum::<u32>();
+        if blocks.contains(&0) || games % 2 == 0 {
+            return Err(anyhow!("invalid series format {value}"));
+        }
+        if games > MAX_SERIES_GAMES {
+            return Err(anyhow!("series format {value} has more than {MAX_SERIES_GAMES} games"));
+        }
+        Ok(SeriesFormat { blocks })
+    }
+}
+
+impl From<SeriesFormat> for String {
+    fn from(value: SeriesFormat) -> Self {
+        let blocks: Vec<_> = value.blocks.iter().map(u32::to_string).collect();
+        blocks.join("-")
+    }
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self> {
+        if Path::new(path).exists() {
+            utils::read_json(path)
+        } else {
+            Ok(Config::default())
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> { utils::write_json(self, path) }
+
+    #[must_use]
+    pub fn predictor(&self) -> &'static dyn Predictor {
+        match self.rating_model {
+            RatingModel::Elo => &Elo,
+            RatingModel::Glicko => &Glicko,
+            RatingModel::BradleyTerry => &BradleyTerry,
+            RatingModel::Srs => &Srs,
+            RatingModel::Pythagorean => &Pythagorean,
+        }
+    }
+
+    #[must_use]
+    pub fn home_bonus(&self, neutral: bool) -> f64 {
+        if neutral {
+            0.0
+        } else {
+            self.home_advantage
+        }
+    }
+
+    pub fn fit_home_advantage(&mut self, games: &[Game]) {
+        let played = games.iter().filter(|x| !x.neutral && x.home_score != x.away_score);
+        let (home_wins, total) = played.fold((0u32, 0u32), |(wins, total), x| {
+            (wins + u32::from(x.home_score > x.away_score), total + 1)
+        });
+        if home_wins == 0 || home_wins == total {
+            return;
+        }
+        let rate = f64::from(home_wins) / f64::from(total);
+        self.home_advantage = 400.0 * (rate / (1.0 - rate)).log10();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn homes(format: &SeriesFormat) -> Vec<bool> {
+        (1..=format.wins_needed() * 2 - 1).map(|x| format.higher_seed_home(x)).collect()
+    }
+
+    #[test]
+    fn parses_series_formats() {
+        let short = SeriesFormat::try_from(String::from("2-2-1")).unwrap();
+        assert_eq!(short.wins_needed(), 3);
+        assert_eq!(homes(&short), [true, true, false, false, true]);
+        let finals = SeriesFormat::try_from(String::from("2-3-2")).unwrap();
+        assert_eq!(finals.wins_needed(), 4);
+        assert_eq!(homes(&finals), [true, true, false, false, false, true, true]);
+    }
+
+    #[test]
+    fn rejects_invalid_series_formats() {
+        for format in ["2-2", "0-1", "9-9-1", "a-1"] {
+            assert!(SeriesFormat::try_from(String::from(format)).is_err(), "{format}");
+        }
+        assert!(SeriesFormat::try_from(String::from("8-8-1")).is_ok());
+    }
+}
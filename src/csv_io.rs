@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::registry::{GameRecord, TeamRegistry};
+use crate::types::{
+    Conference, Division, Game, GlickoState, PointRatings, RatingChange, Record, Team, TeamId,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TeamRow {
+    #[serde(default)]
+    id: Option<TeamId>,
+    name: String,
+    full_name: String,
+    conference: Conference,
+    division: Division,
+    elo: f64,
+    season_wins: u32,
+    season_loses: u32,
+    #[serde(default)]
+    point_differential: i64,
+    #[serde(default)]
+    head_to_head: String,
+    #[serde(default)]
+    rd: Option<f64>,
+    #[serde(default)]
+    volatility: Option<f64>,
+    #[serde(default)]
+    last_played: Option<DateTime<Utc>>,
+    #[serde(default)]
+    srs: Option<f64>,
+    #[serde(default)]
+    pythagorean: Option<f64>,
+    #[serde(default)]
+    offense: Option<f64>,
+    #[serde(default)]
+    defense: Option<f64>,
+}
+
+impl From<&Team> for TeamRow {
+    fn from(value: &Team) -> Self {
+        TeamRow {
+            id: value.id,
+            name: value.name.clone(),
+            full_name: value.full_name.clone(),
+            conference: value.conference,
+            division: value.division,
+            elo: value.elo,
+            season_wins: value.season_wins,
+            season_loses: value.season_loses,
+            point_differential: value.point_differential,
+            head_to_head: value
+                .head_to_head
+                .iter()
+                .map(|(name, record)| format!("{name}:{}-{}", record.wins, record.loses))
+                .collect::<Vec<_>>()
+                .join(" "),
+            rd: value.glicko.map(|x| x.rd),
+            volatility: value.glicko.map(|x| x.volatility),
+            last_played: value.glicko.and_then(|x| x.last_played),
+            srs: value.points.map(|x| x.srs),
+            pythagorean: value.points.map(|x| x.pythagorean),
+            offense: value.points.map(|x| x.offense),
+            defense: value.points.map(|x| x.defense),
+        }
+    }
+}
+
+fn parse_head_to_head(value: &str) -> Result<BTreeMap<String, Record>> {
+    let mut result = BTreeMap::new();
+    for entry in value.split_whitespace() {
+        let invalid = || anyhow!("invalid head to head record {entry}, expected e.g. BOS:2-1");
+        let (name, record) = entry.split_once(':').ok_or_else(invalid)?;
+        let (wins, loses) = record.split_once('-').ok_or_else(invalid)?;
+        let record = Record { wins: wins.parse()?, loses: loses.parse()? };
+        result.insert(name.to_uppercase(), record);
+    }
+    Ok(result)
+}
+
+impl TryFrom<TeamRow> for Team {
+    type Error = anyhow::Error;
+
+    fn try_from(value: TeamRow) -> Result<Self> {
+        Ok(Team {
+            id: value.id,
+            name: value.name,
+            full_name: value.full_name,
+            conference: value.conference,
+            division: value.division,
+            elo: value.elo,
+            season_wins: value.season_wins,
+            season_loses: value.season_loses,
+            head_to_head: parse_head_to_head(&value.head_to_head)?,
+            point_differential: value.point_differential,
+            glicko: value.rd.zip(value.volatility).map(|(rd, volatility)| GlickoState {
+                rd,
+                volatility,
+                last_played: value.last_played,
+            }),
+            points: value.srs.zip(value.pythagorean).map(|(srs, pythagorean)| PointRatings {
+                srs,
+                pythagorean,
+                offense: value.offense.unwrap_or_default(),
+                defense: value.defense.unwrap_or_default(),
+            }),
+        })
+    }
+}
+
+pub fn write_teams(teams: &[Team], path: &str) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for team in teams {
+        writer.serialize(TeamRow::from(team))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_teams(path: &str) -> Result<Vec<Team>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let rows = reader.deserialize::<TeamRow>().collect::<Result<Vec<_>, _>>()?;
+    rows.into_iter().map(Team::try_from).collect()
+}
+
+pub fn write_games(games: &[Game], teams: &[Team], path: &str) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for game in games {
+        writer.serialize(GameRecord::new(game, teams)?)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_history(history: &[RatingChange], path: &str) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for change in history {
+        writer.serialize(change)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_history(path: &str) -> Result<Vec<RatingChange>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let history = reader.deserialize().collect::<Result<Vec<_>, _>>()?;
+    Ok(history)
+}
+
+pub fn read_games(path: &str, teams: &[Team]) -> Result<Vec<Game>> {
+    let registry = TeamRegistry::new(teams)?;
+    let mut reader = csv::Reader::from_path(path)?;
+    reader.deserialize().map(|x| registry.game(x?)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::test_utils::{game, team};
+
+    fn teams() -> Vec<Team> {
+        let mut boston = Team {
+            id: Some(TeamId(2)),
+            full_name: "Boston Celtics".into(),
+            elo: 1012.5,
+            season_wins: 3,
+            season_loses: 1,
+            point_differential: -7,
+            ..team("BOS")
+        };
+        boston.head_to_head.insert("LAL".into(), Record { wins: 2, loses: 1 });
+        boston.glicko = Some(GlickoState { rd: 80.0, volatility: 0.06, last_played: None });
+        boston.points =
+            Some(PointRatings { srs: 4.5, pythagorean: 0.6, offense: 3.0, defense: -1.5 });
+        let mut lakers = Team {
+            id: Some(TeamId(14)),
+            conference: Conference::West,
+            division: Division::Pacific,
+            ..team("LAL")
+        };
+        lakers.head_to_head.insert("BOS".into(), Record { wins: 1, loses: 2 });
+        vec![boston, lakers]
+    }
+
+    fn path(name: &str) -> String {
+        let file = format!("nba-{name}-{}.csv", std::process::id());
+        env::temp_dir().join(file).to_str().unwrap().into()
+    }
+
+    #[test]
+    fn teams_round_trip() {
+        let (teams, path) = (teams(), path("teams"));
+        write_teams(&teams, &path).unwrap();
+        let read = read_teams(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(format!("{read:?}"), format!("{teams:?}"));
+    }
+
+    #[test]
+    fn games_round_trip() {
+        let (teams, path) = (teams(), path("games"));
+        let games = vec![
+            Game {
+                id: 7,
+                date: "2023-10-24T23:30:00Z".parse().unwrap(),
+                period: 5,
+                ..game(0, 1, 108, 104)
+            },
+            Game {
+                id: 8,
+                date: "2024-06-06T00:00:00Z".parse().unwrap(),
+                neutral: true,
+                status: "7:30 pm ET".into(),
+                period: 0,
+                postseason: true,
+                ..game(1, 0, 0, 0)
+            },
+        ];
+        write_games(&games, &teams, &path).unwrap();
+        let read = read_games(&path, &teams).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(format!("{read:?}"), format!("{games:?}"));
+    }
+}
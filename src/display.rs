@@ -1,10 +1,22 @@
 use std::cell::RefCell;
-use std::fs;
+use std::path::Path;
 
 use anyhow::Result;
 
-use crate::simulate::{ConferenceData, RoundData};
+use crate::backtest::{BacktestResult, Metrics};
+use crate::montecarlo::MonteCarloResult;
+use crate::process_data::Exclusion;
+use crate::simulate::{self, ConferenceData, RoundData, Score};
 use crate::types::{Game, Team};
+use crate::utils::{self, Paths};
+
+const LADDER: &str = include_str!("../files/ladder.txt");
+
+fn point_info(team: &Team) -> String {
+    team.points.map_or_else(String::new, |x| {
+        format!(" srs: {:>+6.2} pyth: {:>4.1}", x.srs, x.pythagorean * 82.0)
+    })
+}
 
 pub fn display_by_elo(teams: &[RefCell<Team>]) {
     let mut ordered: Vec<_> = teams.iter().map(RefCell::borrow).collect();
@@ -13,13 +25,15 @@ pub fn display_by_elo(teams: &[RefCell<Team>]) {
     println!("\nTeams by elo based on seasons from 18-19 to 22-23:\n");
     for (i, (better, worse)) in ordered.into_iter().zip(worse).enumerate() {
         println!(
-            "{:>2}. {:<23} elo: {:>7.2} | {:>2}. {:<23} elo: {:>7.2}",
+            "{:>2}. {:<23} elo: {:>7.2}{} | {:>2}. {:<23} elo: {:>7.2}{}",
             i + 1,
             better.full_name,
             better.elo,
+            point_info(&better),
             i + 16,
             worse.full_name,
-            worse.elo
+            worse.elo,
+            point_info(&worse)
         );
     }
     println!();
@@ -46,8 +60,83 @@ pub fn display_by_wins(west: &[Team], east: &[Team]) {
     println!();
 }
 
+pub fn display_odds(result: &MonteCarloResult) {
+    println!("\nChampionship odds after {} simulations (seed {}):\n", result.runs, result.seed);
+    println!(
+        "{:>2}  {:<23} {:>6} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+        "", "Team", "Wins", "Play-in", "Playoffs", "Semis", "Conf F", "Finals", "Title"
+    );
+    for (i, team) in result.teams.iter().enumerate() {
+        println!(
+            "{:>2}. {:<23} {:>6.2} {:>7.2}% {:>7.2}% {:>7.2}% {:>7.2}% {:>7.2}% {:>7.2}%",
+            i + 1,
+            team.full_name,
+            team.average_wins,
+            team.play_in * 100.0,
+            team.playoffs * 100.0,
+            team.semifinals * 100.0,
+            team.conference_finals * 100.0,
+            team.finals * 100.0,
+            team.champion * 100.0,
+        );
+    }
+    println!();
+}
+
+fn season_name(metrics: &Metrics) -> String {
+    metrics.season.map_or_else(|| "Overall".into(), |x| format!("{x}-{:02}", (x + 1) % 100))
+}
+
+pub fn display_backtest(result: &BacktestResult) {
+    println!("\nBacktest of {:?} ratings:\n", result.rating_model);
+    println!("{:<8} {:>6} {:>7} {:>9} {:>9}", "Season", "Games", "Brier", "Log loss", "Accuracy");
+    for metrics in result.seasons.iter().chain([&result.overall]) {
+        println!(
+            "{:<8} {:>6} {:>7.4} {:>9.4} {:>8.2}%",
+            season_name(metrics),
+            metrics.games,
+            metrics.brier,
+            metrics.log_loss,
+            metrics.accuracy * 100.0
+        );
+    }
+    for metrics in result.seasons.iter().chain([&result.overall]) {
+        println!("\nCalibration of home win probability, {}:\n", season_name(metrics));
+        println!("{:<11} {:>6} {:>9} {:>8}", "Predicted", "Games", "Expected", "Actual");
+        for bucket in metrics.calibration.iter().filter(|x| x.games > 0) {
+            println!(
+                "{:>3.0}% - {:>3.0}% {:>6} {:>8.2}% {:>7.2}%",
+                bucket.from * 100.0,
+                bucket.to * 100.0,
+                bucket.games,
+                bucket.expected * 100.0,
+                bucket.actual * 100.0
+            );
+        }
+    }
+    println!();
+}
+
+pub fn display_excluded(excluded: &[(Game, Exclusion)], teams: &[Team], included: usize) {
+    println!("Using {included} games, excluded {}", excluded.len());
+    for (game, exclusion) in excluded {
+        println!(
+            "{}: {} {:>3} - {:<3} {} | status: {:?}, period: {}{} | {}",
+            game.date.date_naive(),
+            teams[game.home_team_idx].name,
+            game.home_score,
+            game.away_score,
+            teams[game.away_team_idx].name,
+            game.status,
+            game.period,
+            if game.postseason { ", postseason" } else { "" },
+            exclusion.reason()
+        );
+    }
+}
+
 pub fn print_game_info(
-    game: &Game, home: &Team, away: &Team, expected: f64, home_old: f64, away_old: f64, change: f64,
+    game: &Game, home: &Team, away: &Team, expected: f64, home_old: f64, away_old: f64,
 ) {
     println!(
         "{}: {} {:>3} - {:<3} {} | exp: {:>2}% | {:>7.2} -> {:>7.2} ({:+06.2}), {:>7.2} -> \
@@ -60,40 +149,48 @@ pub fn print_game_info(
         (expected * 100.0) as u32,
         home_old,
         home.elo,
-        change,
+        home.elo - home_old,
         away_old,
         away.elo,
-        -change
+        away.elo - away_old
     );
 }
 
+fn overtime(periods: u32) -> String {
+    match periods {
+        0..=4 => String::new(),
+        5 => " OT".into(),
+        _ => format!(" {}OT", periods - 4),
+    }
+}
+
 pub fn print_simulated_game_info(
-    name: &str, home: &Team, away: &Team, home_win: bool, expected: f64, home_old: f64,
-    away_old: f64, change: f64,
+    name: &str, home: &Team, away: &Team, score: &Score, expected: f64, home_old: f64,
+    away_old: f64,
 ) {
     println!(
-        "{:<35}: {} {} - {} {} | exp: {:>2}% | {:>7.2} -> {:>7.2} ({:+06.2}), {:>7.2} -> {:>7.2} \
-         ({:+06.2})",
+        "{:<35}: {} {:>3} - {:<3} {}{:<4} | exp: {:>2}% | {:>7.2} -> {:>7.2} ({:+06.2}), {:>7.2} \
+         -> {:>7.2} ({:+06.2})",
         name,
         &home.name,
-        if home_win { "W" } else { "L" },
-        if !home_win { "W" } else { "L" },
+        score.home,
+        score.away,
         &away.name,
+        overtime(score.periods),
         (expected * 100.0) as u32,
         home_old,
         home.elo,
-        change,
+        home.elo - home_old,
         away_old,
         away.elo,
-        -change
+        away.elo - away_old
     );
 }
 
 pub fn display_ladder(
     west: &ConferenceData, east: &ConferenceData, finals: &RoundData, winner: &str,
 ) -> Result<()> {
-    let ladder: String = fs::read_to_string("files/ladder.txt")?;
-    let mut bytes = ladder.into_bytes();
+    let mut bytes = LADDER.as_bytes().to_vec();
     let mut replace = |value: &[u8; 3], replacement: &[u8]| {
         if let Some(pos) = bytes.windows(3).position(|w| w == value) {
             bytes[pos..(3 + pos)].copy_from_slice(&replacement[..3]);
@@ -126,3 +223,21 @@ pub fn display_ladder(
     println!("{ladder}");
     Ok(())
 }
+
+pub fn report(paths: &Paths) -> Result<()> {
+    if Path::new(&paths.processed).exists() {
+        display_by_elo(&utils::load_teams_cell(&paths.processed)?);
+    }
+    if Path::new(&paths.after_season).exists() {
+        let teams = utils::load_teams(&paths.after_season)?;
+        let (west, east) = simulate::partition(teams, &mut simulate::standings_rng(paths)?);
+        display_by_wins(&west, &east);
+    }
+    if Path::new(&paths.montecarlo).exists() {
+        display_odds(&utils::read_json(&paths.montecarlo)?);
+    }
+    if Path::new(&paths.backtest).exists() {
+        display_backtest(&utils::read_json(&paths.backtest)?);
+    }
+    Ok(())
+}
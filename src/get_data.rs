@@ -1,32 +1,15 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, Utc};
-use reqwest::{IntoUrl, Url};
-use serde::de::DeserializeOwned;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::types::{Conference, Division, Game, Team};
-use crate::utils;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct TeamResponse {
-    data: Vec<TeamData>,
-    meta: MetaData,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct GameResponse {
-    data: Vec<GameData>,
-    meta: MetaData,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct MetaData {
-    total_pages: u32,
-    current_page: u32,
-    next_page: Option<u32>,
-    per_page: u32,
-    total_count: u32,
-}
+use crate::config::Config;
+use crate::provider::Provider;
+use crate::registry::TeamRegistry;
+use crate::types::{Conference, Division, Game, Team, TeamId};
+use crate::utils::{self, Paths};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GameData {
@@ -43,15 +26,29 @@ pub struct GameData {
     visitor_team: TeamData,
 }
 
-impl From<GameData> for Game {
-    fn from(value: GameData) -> Self {
-        Game {
-            date: value.date,
-            home_team_idx: (value.home_team.id - 1) as usize,
-            away_team_idx: (value.visitor_team.id - 1) as usize,
-            home_score: value.home_team_score,
-            away_score: value.visitor_team_score,
-        }
+fn is_neutral_site(date: DateTime<Utc>) -> bool {
+    let bubble_start = NaiveDate::from_ymd_opt(2020, 7, 30).unwrap();
+    let bubble_end = NaiveDate::from_ymd_opt(2020, 10, 11).unwrap();
+    (bubble_start..=bubble_end).contains(&date.date_naive())
+}
+
+impl GameData {
+    fn into_game(self, registry: &TeamRegistry) -> Result<Game> {
+        Ok(Game {
+            id: self.id,
+            date: self.date,
+            season: self.season,
+            home_team_idx: registry
+                .resolve(TeamId(self.home_team.id), &self.home_team.abbreviation)?,
+            away_team_idx: registry
+                .resolve(TeamId(self.visitor_team.id), &self.visitor_team.abbreviation)?,
+            home_score: self.home_team_score,
+            away_score: self.visitor_team_score,
+            neutral: is_neutral_site(self.date),
+            status: self.status,
+            period: self.period,
+            postseason: self.postseason,
+        })
     }
 }
 
@@ -69,69 +66,95 @@ pub struct TeamData {
 impl From<TeamData> for Team {
     fn from(value: TeamData) -> Self {
         Team {
+            id: Some(TeamId(value.id)),
             name: value.abbreviation,
             full_name: value.full_name,
             conference: value.conference,
             division: value.division,
-            elo: 1000.0,
+            elo: Config::default().initial_elo,
             season_wins: 0,
             season_loses: 0,
+            head_to_head: BTreeMap::new(),
+            point_differential: 0,
+            glicko: None,
+            points: None,
         }
     }
 }
 
-async fn get_json_response<T, U>(url: T) -> Result<U>
-where
-    T: IntoUrl,
-    U: DeserializeOwned,
-{
-    let response = reqwest::get(url).await?;
-    if !response.status().is_success() {
-        return Err(anyhow!("bad response error"));
-    }
-    let json = response.json().await?;
-    Ok(json)
-}
-
-pub async fn get_teams() -> Result<()> {
+pub async fn get_teams<P>(provider: &P, paths: &Paths) -> Result<()>
+where P: Provider {
     println!("Getting team data...");
-    let url = "https://www.balldontlie.io/api/v1/teams";
-    let mut team_response: TeamResponse = get_json_response(url).await?;
-    team_response.data.sort_by_key(|x| x.id);
-    println!("{:?}", team_response.meta);
-    let result: Vec<Team> = team_response.data.into_iter().map(Team::from).collect();
-    utils::write_json(&result, "files/teams.json")?;
+    let mut teams = provider.teams().await?;
+    teams.sort_by_key(|x| x.id);
+    let result: Vec<Team> = teams.into_iter().map(Team::from).collect();
+    utils::write_json(&result, &paths.teams)?;
     Ok(())
 }
 
-async fn get_games(season_query: String, path: &str) -> Result<()> {
-    let url_base = "https://www.balldontlie.io/api/v1/games";
-    let mut query: Vec<(_, String)> = vec![("page", "0".into()), ("per_page", "100".into())];
-    query.extend(season_query.split(',').map(|x| ("seasons[]", x.into())));
-    let mut games: Vec<Game> = Vec::new();
-    loop {
-        let game_response: GameResponse =
-            get_json_response(Url::parse_with_params(url_base, &query)?).await?;
-        println!("{:?}", game_response.meta);
-        games.extend(game_response.data.into_iter().map(Game::from));
-        let Some(next) = game_response.meta.next_page else {
-            break;
-        };
-        query[0].1 = next.to_string();
+async fn get_games<P>(
+    provider: &P, seasons: &[u32], since: Option<NaiveDate>, teams: &[Team],
+) -> Result<Vec<Game>>
+where P: Provider {
+    let registry = TeamRegistry::new(teams)?;
+    let games = provider.games(seasons, since).await?.into_iter().map(|x| x.into_game(&registry));
+    let mut games = games.collect::<Result<Vec<_>>>()?;
+    if let Some(since) = since {
+        games.retain(|x| x.date.date_naive() >= since);
     }
     games.sort_by_key(|x| x.date);
-    utils::write_json(&games, path)?;
-    Ok(())
+    Ok(games)
 }
 
-pub async fn get_previous_games() -> Result<()> {
+pub async fn get_previous_games<P>(provider: &P, paths: &Paths) -> Result<()>
+where P: Provider {
     println!("Getting previous games...");
-    get_games("2018,2019,2020,2021,2022".into(), "files/prev_games.json").await?;
+    let teams = utils::load_teams(&paths.teams)?;
+    let games = get_games(provider, &[2018, 2019, 2020, 2021, 2022], None, &teams).await?;
+    utils::write_games(&games, &teams, &paths.previous_games)?;
     Ok(())
 }
 
-pub async fn get_season_games() -> Result<()> {
+pub async fn get_season_games<P>(provider: &P, paths: &Paths) -> Result<()>
+where P: Provider {
     println!("Getting games for this season...");
-    get_games("2023".into(), "files/games.json").await?;
+    let teams = utils::load_teams(&paths.teams)?;
+    let games = get_games(provider, &[2023], None, &teams).await?;
+    utils::write_games(&games, &teams, &paths.games)?;
+    Ok(())
+}
+
+pub async fn update_season_games<P>(provider: &P, paths: &Paths) -> Result<()>
+where P: Provider {
+    if !Path::new(&paths.games).exists() {
+        return get_season_games(provider, paths).await;
+    }
+    let teams = utils::load_teams(&paths.teams)?;
+    let mut games = utils::load_games(&paths.games, &teams)?;
+    if games.iter().any(|x| x.id == 0) {
+        return Err(anyhow!("{} has games without ids, download it again", paths.games));
+    }
+    let since = games.iter().filter(|x| x.is_final()).map(|x| x.date.date_naive()).max();
+    match since {
+        Some(date) => println!("Getting games for this season since {date}..."),
+        None => println!("Getting games for this season..."),
+    }
+    let new_games = get_games(provider, &[2023], since, &teams).await?;
+    let mut positions: HashMap<u32, usize> =
+        games.iter().enumerate().map(|(i, x)| (x.id, i)).collect();
+    let (mut added, mut updated) = (0, 0);
+    for game in new_games {
+        if let Some(&i) = positions.get(&game.id) {
+            games[i] = game;
+            updated += 1;
+        } else {
+            positions.insert(game.id, games.len());
+            games.push(game);
+            added += 1;
+        }
+    }
+    games.sort_by_key(|x| x.date);
+    utils::write_games(&games, &teams, &paths.games)?;
+    println!("Added {added} and updated {updated} games");
     Ok(())
 }
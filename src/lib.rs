@@ -1,8 +1,21 @@
 #![warn(clippy::pedantic)]
 
+pub mod backtest;
+pub mod bradley_terry;
+pub mod chart;
+pub mod config;
+pub mod csv_io;
 pub mod display;
 pub mod get_data;
+pub mod montecarlo;
+pub mod point_ratings;
+pub mod predictor;
 pub mod process_data;
+pub mod provider;
+pub mod registry;
 pub mod simulate;
+pub mod standings;
+#[cfg(test)]
+mod test_utils;
 pub mod types;
 pub mod utils;
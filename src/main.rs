@@ -1,37 +1,343 @@
+use std::path::Path;
+
 use anyhow::Result;
-use nba::get_data::{get_previous_games, get_season_games, get_teams};
-use nba::process_data::process_games;
+use clap::{Parser, Subcommand, ValueEnum};
+use nba::backtest::backtest;
+use nba::chart::elo_chart;
+use nba::csv_io;
+use nba::display::report;
+use nba::get_data::{get_previous_games, get_season_games, get_teams, update_season_games};
+use nba::montecarlo::simulate_many;
+use nba::process_data::{fit_k_factor, process_games, update_ratings};
+use nba::provider::{BallDontLie, Fixtures, Provider, BALLDONTLIE_URL};
 use nba::simulate::{simulate_postseason, simulate_season};
-use nba::utils;
-use std::path::Path;
+use nba::utils::{self, Paths};
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    if !Path::new("files/teams.json").exists() {
-        get_teams().await?;
+#[derive(Parser)]
+#[command(about = "NBA season and playoff simulator, runs interactively without a subcommand")]
+struct Cli {
+    /// Directory with data files
+    #[arg(long, global = true, default_value = "files")]
+    dir: String,
+    /// Base URL of the balldontlie API
+    #[arg(long, global = true, default_value = BALLDONTLIE_URL)]
+    api_url: String,
+    /// Key sent in the authorization header of API requests
+    #[arg(long, global = true, env = "BALLDONTLIE_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+    /// Read teams and games from a local directory instead of the API
+    #[arg(long, global = true)]
+    fixtures: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Download team data and games
+    Fetch {
+        #[arg(long, value_enum, default_value_t = Data::All)]
+        data: Data,
+        /// Only fetch season games since the last final game and merge them into existing ones
+        #[arg(long)]
+        update: bool,
+    },
+    /// Compute ratings from previous seasons
+    Process {
+        /// Abbreviation of a team to print games of, `*` for all
+        #[arg(long)]
+        team: Option<String>,
+        /// Fit the k-factor to previous games before processing
+        #[arg(long)]
+        fit_k: bool,
+        /// Apply only games the existing ratings don't include yet
+        #[arg(long, conflicts_with = "fit_k")]
+        update: bool,
+        /// Previous games
+        #[arg(long)]
+        input: Option<String>,
+        /// Processed ratings
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Simulate the rest of the current season
+    SimulateSeason {
+        /// Abbreviation of a team to print games of, `*` for all
+        #[arg(long)]
+        team: Option<String>,
+        /// Processed ratings
+        #[arg(long)]
+        input: Option<String>,
+        /// Games of the current season
+        #[arg(long)]
+        games: Option<String>,
+        /// Standings after the season
+        #[arg(long)]
+        output: Option<String>,
+        /// Seed of the random number generator
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Simulate play-in and playoffs from simulated standings
+    SimulatePlayoffs {
+        /// Abbreviation of a team to print games of, `*` for all
+        #[arg(long)]
+        team: Option<String>,
+        /// Standings after the season
+        #[arg(long)]
+        input: Option<String>,
+        /// Seed of the random number generator
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Simulate season and playoffs many times and aggregate the results
+    Montecarlo {
+        /// Number of simulations
+        #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u32).range(1..))]
+        runs: u32,
+        /// Processed ratings
+        #[arg(long)]
+        input: Option<String>,
+        /// Games of the current season
+        #[arg(long)]
+        games: Option<String>,
+        /// Aggregated results
+        #[arg(long)]
+        output: Option<String>,
+        /// Seed of the random number generator
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Measure how well the configured rating model predicted past games
+    Backtest {
+        /// Number of probability buckets in the calibration tables
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=100))]
+        buckets: u32,
+        /// Days between refits of batch fitted ratings, which only use earlier games
+        #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(u32).range(1..))]
+        refit_days: u32,
+        /// Backtest results
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Print ratings, standings, odds and backtest results from existing files
+    Report,
+    /// Draw an SVG chart of ratings over time
+    Chart {
+        /// Abbreviations of teams to draw, all teams if empty
+        #[arg(long, value_delimiter = ',')]
+        teams: Vec<String>,
+        /// Use ratings of the simulated season instead of processed games
+        #[arg(long)]
+        season: bool,
+        /// Rating history
+        #[arg(long)]
+        input: Option<String>,
+        /// SVG file to write
+        #[arg(long, default_value = "elo.svg")]
+        output: String,
+    },
+    /// Write a data file as CSV
+    Export {
+        #[arg(long, value_enum)]
+        data: Table,
+        /// CSV file to write
+        #[arg(long)]
+        output: String,
+    },
+    /// Replace a data file with the contents of a CSV file
+    Import {
+        #[arg(long, value_enum)]
+        data: Table,
+        /// CSV file to read
+        #[arg(long)]
+        input: String,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Data {
+    Teams,
+    Previous,
+    Season,
+    All,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Table {
+    Teams,
+    Previous,
+    Season,
+    Processed,
+    AfterSeason,
+    History,
+    SeasonHistory,
+}
+
+impl Table {
+    fn path(self, paths: &Paths) -> &str {
+        match self {
+            Table::Teams => &paths.teams,
+            Table::Previous => &paths.previous_games,
+            Table::Season => &paths.games,
+            Table::Processed => &paths.processed,
+            Table::AfterSeason => &paths.after_season,
+            Table::History => &paths.history,
+            Table::SeasonHistory => &paths.season_history,
+        }
+    }
+
+    fn is_games(self) -> bool { matches!(self, Table::Previous | Table::Season) }
+
+    fn is_history(self) -> bool { matches!(self, Table::History | Table::SeasonHistory) }
+}
+
+fn export(paths: &Paths, data: Table, output: &str) -> Result<()> {
+    let path = data.path(paths);
+    if data.is_games() {
+        let teams = utils::load_teams(&paths.teams)?;
+        csv_io::write_games(&utils::load_games(path, &teams)?, &teams, output)
+    } else if data.is_history() {
+        csv_io::write_history(&utils::read_json::<Vec<_>>(path)?, output)
+    } else {
+        csv_io::write_teams(&utils::load_teams(path)?, output)
+    }
+}
+
+fn import(paths: &Paths, data: Table, input: &str) -> Result<()> {
+    let path = data.path(paths);
+    if data.is_games() {
+        let teams = utils::load_teams(&paths.teams)?;
+        utils::write_games(&csv_io::read_games(input, &teams)?, &teams, path)
+    } else if data.is_history() {
+        utils::write_json(&csv_io::read_history(input)?, path)
+    } else {
+        utils::write_json(&csv_io::read_teams(input)?, path)
+    }
+}
+
+fn set(path: &mut String, value: Option<String>) {
+    if let Some(value) = value {
+        *path = value;
+    }
+}
+
+async fn fetch<P>(provider: &P, paths: &Paths, data: Data, update: bool) -> Result<()>
+where P: Provider {
+    if matches!(data, Data::Teams | Data::All) {
+        get_teams(provider, paths).await?;
+    }
+    if matches!(data, Data::Previous | Data::All) {
+        get_previous_games(provider, paths).await?;
+    }
+    if matches!(data, Data::Season | Data::All) {
+        if update {
+            update_season_games(provider, paths).await?;
+        } else {
+            get_season_games(provider, paths).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn interactive<P>(paths: &Paths, provider: &P) -> Result<()>
+where P: Provider {
+    if !Path::new(&paths.teams).exists() {
+        get_teams(provider, paths).await?;
     }
     println!("Got team data");
-    if !Path::new("files/prev_games.json").exists() {
-        get_previous_games().await?;
+    if !Path::new(&paths.previous_games).exists() {
+        get_previous_games(provider, paths).await?;
     }
     println!("Got previous games");
-    if !Path::new("files/processed.json").exists() {
-        process_games()?;
+    if !Path::new(&paths.processed).exists() {
+        if utils::ask("fit k-factor to previous games?") {
+            fit_k_factor(paths)?;
+        }
+        process_games(paths, None)?;
     }
     println!("Processed games");
-    if !Path::new("files/games.json").exists() || utils::ask("download current season again?") {
-        get_season_games().await?;
+    if !Path::new(&paths.games).exists() || utils::ask("download current season again?") {
+        get_season_games(provider, paths).await?;
+    } else if utils::ask("fetch new games of current season?") {
+        update_season_games(provider, paths).await?;
     }
     println!("Got season games");
     loop {
-        if !Path::new("files/after_season.json").exists() || utils::ask("simulate season again?") {
-            simulate_season()?;
+        if !Path::new(&paths.after_season).exists() || utils::ask("simulate season again?") {
+            simulate_season(paths, None, None)?;
         }
         println!("Simulated season");
-        simulate_postseason()?;
+        simulate_postseason(paths, None, None)?;
+        if utils::ask("run monte carlo simulation?") {
+            simulate_many(paths, utils::ask_number("how many simulations?"), None)?;
+        }
         if utils::ask("done?") {
             break;
         }
     }
     Ok(())
 }
+
+async fn run<P>(command: Option<Command>, mut paths: Paths, provider: &P) -> Result<()>
+where P: Provider {
+    let Some(command) = command else {
+        return interactive(&paths, provider).await;
+    };
+    match command {
+        Command::Fetch { data, update } => fetch(provider, &paths, data, update).await?,
+        Command::Process { team, fit_k, update, input, output } => {
+            set(&mut paths.previous_games, input);
+            set(&mut paths.processed, output);
+            let team = Some(team.as_deref().unwrap_or_default());
+            if update {
+                update_ratings(&paths, team)?;
+            } else {
+                if fit_k {
+                    fit_k_factor(&paths)?;
+                }
+                process_games(&paths, team)?;
+            }
+        },
+        Command::SimulateSeason { team, input, games, output, seed } => {
+            set(&mut paths.processed, input);
+            set(&mut paths.games, games);
+            set(&mut paths.after_season, output);
+            simulate_season(&paths, Some(team.as_deref().unwrap_or_default()), seed)?;
+        },
+        Command::SimulatePlayoffs { team, input, seed } => {
+            set(&mut paths.after_season, input);
+            simulate_postseason(&paths, Some(team.as_deref().unwrap_or_default()), seed)?;
+        },
+        Command::Montecarlo { runs, input, games, output, seed } => {
+            set(&mut paths.processed, input);
+            set(&mut paths.games, games);
+            set(&mut paths.montecarlo, output);
+            simulate_many(&paths, runs, seed)?;
+        },
+        Command::Backtest { buckets, refit_days, output } => {
+            set(&mut paths.backtest, output);
+            backtest(&paths, buckets, refit_days)?;
+        },
+        Command::Report => report(&paths)?,
+        Command::Chart { teams, season, input, output } => {
+            let history = if season { &mut paths.season_history } else { &mut paths.history };
+            set(history, input);
+            elo_chart(&utils::read_json::<Vec<_>>(history)?, &teams, &output)?;
+        },
+        Command::Export { data, output } => export(&paths, data, &output)?,
+        Command::Import { data, input } => import(&paths, data, &input)?,
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let paths = Paths::new(&cli.dir);
+    if let Some(dir) = cli.fixtures {
+        return run(cli.command, paths, &Fixtures::new(dir)).await;
+    }
+    let provider = BallDontLie::new(&cli.api_url, cli.api_key)?.with_cache(paths.cache.clone());
+    run(cli.command, paths, &provider).await
+}
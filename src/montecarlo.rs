@@ -0,0 +1,166 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::Result;
+use rand::random;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::simulate::{self, ConferenceData, PostseasonData, RoundData};
+use crate::types::{Conference, Game, Team};
+use crate::utils::{self, Paths};
+use crate::{display, process_data};
+
+#[derive(Debug, Clone, Default)]
+struct Tally {
+    wins: Vec<u32>,
+    seeds: Vec<u32>,
+    play_in: u32,
+    playoffs: u32,
+    semifinals: u32,
+    conference_finals: u32,
+    finals: u32,
+    champion: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonteCarloResult {
+    pub seed: u64,
+    pub runs: u32,
+    pub teams: Vec<TeamOdds>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TeamOdds {
+    pub name: String,
+    pub full_name: String,
+    pub conference: Conference,
+    pub average_wins: f64,
+    pub wins: Vec<f64>,
+    pub seeds: Vec<f64>,
+    pub play_in: f64,
+    pub playoffs: f64,
+    pub semifinals: f64,
+    pub conference_finals: f64,
+    pub finals: f64,
+    pub champion: f64,
+}
+
+impl TeamOdds {
+    fn new(team: &Team, tally: &Tally, runs: u32) -> Self {
+        let runs = f64::from(runs);
+        let probability = |count: u32| f64::from(count) / runs;
+        let total: u32 = tally.wins.iter().zip(0..).map(|(count, wins)| count * wins).sum();
+        TeamOdds {
+            name: team.name.clone(),
+            full_name: team.full_name.clone(),
+            conference: team.conference,
+            average_wins: f64::from(total) / runs,
+            wins: tally.wins.iter().copied().map(probability).collect(),
+            seeds: tally.seeds.iter().copied().map(probability).collect(),
+            play_in: probability(tally.play_in),
+            playoffs: probability(tally.playoffs),
+            semifinals: probability(tally.semifinals),
+            conference_finals: probability(tally.conference_finals),
+            finals: probability(tally.finals),
+            champion: probability(tally.champion),
+        }
+    }
+}
+
+struct MonteCarlo {
+    indices: HashMap<String, usize>,
+    tallies: Vec<Tally>,
+}
+
+impl MonteCarlo {
+    fn new(teams: &[Team]) -> Self {
+        let indices = teams.iter().enumerate().map(|(i, x)| (x.name.clone(), i)).collect();
+        MonteCarlo { indices, tallies: vec![Tally::default(); teams.len()] }
+    }
+
+    fn tally(&mut self, name: &str) -> &mut Tally { &mut self.tallies[self.indices[name]] }
+
+    fn record_standings(&mut self, conference: &[Team]) {
+        for (seed, team) in conference.iter().enumerate() {
+            let tally = self.tally(&team.name);
+            let wins = team.season_wins as usize;
+            if tally.wins.len() <= wins {
+                tally.wins.resize(wins + 1, 0);
+            }
+            tally.wins[wins] += 1;
+            if tally.seeds.len() < conference.len() {
+                tally.seeds.resize(conference.len(), 0);
+            }
+            tally.seeds[seed] += 1;
+            if (6..10).contains(&seed) {
+                tally.play_in += 1;
+            }
+        }
+    }
+
+    fn record_round<F>(&mut self, round: &RoundData, field: F)
+    where F: Fn(&mut Tally) -> &mut u32 {
+        *field(self.tally(&round.team_a)) += 1;
+        *field(self.tally(&round.team_b)) += 1;
+    }
+
+    fn record_conference(&mut self, conference: &ConferenceData) {
+        for round in &conference.round_1 {
+            self.record_round(round, |x| &mut x.playoffs);
+        }
+        for round in &conference.semifinals {
+            self.record_round(round, |x| &mut x.semifinals);
+        }
+        self.record_round(&conference.finals, |x| &mut x.conference_finals);
+    }
+
+    fn record_postseason(&mut self, data: &PostseasonData) {
+        self.record_conference(&data.west);
+        self.record_conference(&data.east);
+        self.record_round(&data.finals, |x| &mut x.finals);
+        self.tally(&data.winner).champion += 1;
+    }
+
+    fn run(
+        &mut self, teams: &[Team], games: &[Game], applied: &BTreeSet<u32>, config: &Config,
+        rng: &mut StdRng,
+    ) {
+        let season: Vec<_> = teams.iter().cloned().map(RefCell::new).collect();
+        simulate::play_season(&season, games, applied, config, "", rng, |_| {});
+        let season = season.into_iter().map(RefCell::into_inner).collect();
+        let (west, east) = simulate::partition(season, rng);
+        self.record_standings(&west);
+        self.record_standings(&east);
+        let data = simulate::play_postseason(west, east, config, "", rng);
+        self.record_postseason(&data);
+    }
+
+    fn into_odds(self, teams: &[Team], runs: u32) -> Vec<TeamOdds> {
+        teams
+            .iter()
+            .zip(&self.tallies)
+            .map(|(team, tally)| TeamOdds::new(team, tally, runs))
+            .collect()
+    }
+}
+
+pub fn simulate_many(paths: &Paths, runs: u32, seed: Option<u64>) -> Result<()> {
+    let config = Config::load(&paths.config)?;
+    let (teams, games, applied) = process_data::load_season(paths, &config)?;
+    let teams: Vec<_> = teams.into_iter().map(RefCell::into_inner).collect();
+    println!("Running {runs} simulations...");
+    let seed = seed.unwrap_or_else(random);
+    let mut rng = utils::seeded_rng(Some(seed));
+    let mut monte_carlo = MonteCarlo::new(&teams);
+    for _ in 0..runs {
+        monte_carlo.run(&teams, &games, &applied, &config, &mut rng);
+    }
+    let mut odds = monte_carlo.into_odds(&teams, runs);
+    odds.sort_by(|x, y| x.champion.total_cmp(&y.champion).reverse());
+    let result = MonteCarloResult { seed, runs, teams: odds };
+    utils::write_json(&result, &paths.montecarlo)?;
+    display::display_odds(&result);
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use anyhow::{anyhow, Result};
+
+use crate::config::Config;
+use crate::types::{Game, PointRatings, Team};
+
+const MAX_ITERATIONS: u32 = 10_000;
+const TOLERANCE: f64 = 0.000_000_1;
+
+#[derive(Clone, Default)]
+struct Totals {
+    games: f64,
+    margin: f64,
+    points_for: f64,
+    points_against: f64,
+    opponents: Vec<usize>,
+}
+
+pub fn latest_season<'a, I>(games: I) -> Vec<&'a Game>
+where I: IntoIterator<Item = &'a Game> {
+    let games: Vec<_> = games.into_iter().collect();
+    let season = games.iter().map(|x| x.season).max();
+    games.into_iter().filter(|x| Some(x.season) == season).collect()
+}
+
+fn home_points(games: &[&Game]) -> Option<f64> {
+    let (total, count) =
+        games.iter().filter(|x| !x.neutral).fold((0.0, 0.0), |(total, count), x| {
+            (total + f64::from(x.home_score) - f64::from(x.away_score), count + 1.0)
+        });
+    (count > 0.0).then(|| total / count)
+}
+
+pub fn fit(teams: &mut [Team], games: &[&Game], config: &mut Config) -> Result<()> {
+    if config.fit_home_advantage {
+        if let Some(home_points) = home_points(games) {
+            config.srs.home_points = home_points;
+        }
+    }
+    let mut totals = vec![Totals::default(); teams.len()];
+    for game in games {
+        let home_points = if game.neutral { 0.0 } else { config.srs.home_points };
+        let margin = f64::from(game.home_score) - f64::from(game.away_score) - home_points;
+        let (home, away) = (game.home_team_idx, game.away_team_idx);
+        for (team, opponent, sign, scored, allowed) in [
+            (home, away, 1.0, game.home_score, game.away_score),
+            (away, home, -1.0, game.away_score, game.home_score),
+        ] {
+            let totals = &mut totals[team];
+            totals.games += 1.0;
+            totals.margin += sign * margin;
+            totals.points_for += f64::from(scored);
+            totals.points_against += f64::from(allowed);
+            totals.opponents.push(opponent);
+        }
+    }
+    let mut srs: Vec<f64> =
+        totals.iter().map(|x| if x.games > 0.0 { x.margin / x.games } else { 0.0 }).collect();
+    let mut converged = false;
+    for _ in 0..MAX_ITERATIONS {
+        // averaged with the previous step, the plain update oscillates on bipartite schedules
+        let mut next: Vec<f64> = totals
+            .iter()
+            .zip(&srs)
+            .map(|(x, &rating)| {
+                if x.games == 0.0 {
+                    return 0.0;
+                }
+                let schedule = x.opponents.iter().map(|&i| srs[i]).sum::<f64>() / x.games;
+                0.5 * (rating + x.margin / x.games + schedule)
+            })
+            .collect();
+        let mean = next.iter().sum::<f64>() / next.iter().fold(0.0, |count, _| count + 1.0);
+        for rating in &mut next {
+            *rating -= mean;
+        }
+        let largest =
+            srs.iter().zip(&next).fold(0.0, |largest: f64, (x, y)| largest.max((x - y).abs()));
+        srs = next;
+        if largest < TOLERANCE {
+            converged = true;
+            break;
+        }
+    }
+    if !converged {
+        return Err(anyhow!("SRS didn't converge after {MAX_ITERATIONS} iterations"));
+    }
+    let (points, games) = totals
+        .iter()
+        .fold((0.0, 0.0), |(points, games), x| (points + x.points_for, games + x.games));
+    if games > 0.0 {
+        config.srs.average_points = points / games;
+    }
+    let average = config.srs.average_points;
+    let exponent = config.srs.pythagorean_exponent;
+    for ((team, totals), srs) in teams.iter_mut().zip(&totals).zip(srs) {
+        team.points = (totals.games > 0.0).then(|| {
+            let scored = totals.points_for.powf(exponent);
+            let allowed = totals.points_against.powf(exponent);
+            PointRatings {
+                srs,
+                pythagorean: scored / (scored + allowed),
+                offense: totals.points_for / totals.games - average,
+                defense: totals.points_against / totals.games - average,
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{game, team};
+
+    fn srs(teams: usize, games: &[Game]) -> Vec<f64> {
+        let mut teams: Vec<_> = (0..teams).map(|x| team(&x.to_string())).collect();
+        let mut config = Config { fit_home_advantage: false, ..Config::default() };
+        config.srs.home_points = 0.0;
+        fit(&mut teams, &games.iter().collect::<Vec<_>>(), &mut config).unwrap();
+        teams.iter().map(|x| x.points.unwrap().srs).collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        for (x, y) in actual.iter().zip(expected) {
+            assert!((x - y).abs() < 0.000_01, "{actual:?} {expected:?}");
+        }
+    }
+
+    #[test]
+    fn fits_round_robin() {
+        let games = [game(0, 1, 110, 100), game(1, 2, 110, 100), game(0, 2, 120, 100)];
+        assert_close(&srs(3, &games), &[10.0, 0.0, -10.0]);
+    }
+
+    #[test]
+    fn converges_on_even_cycle() {
+        // every game goes between {0, 2} and {1, 3}, where the plain update oscillates
+        let games = [(0, 1), (2, 1), (0, 3), (2, 3)].map(|(x, y)| game(x, y, 110, 100));
+        assert_close(&srs(4, &games), &[5.0, -5.0, 5.0, -5.0]);
+    }
+}
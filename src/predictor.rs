@@ -0,0 +1,270 @@
+use std::f64::consts::PI;
+
+use chrono::{DateTime, Utc};
+
+use crate::config::{Config, GlickoConfig};
+use crate::types::{Game, GlickoState, Team};
+use crate::utils;
+
+pub const SCALE: f64 = 173.7178;
+const EPSILON: f64 = 0.000_001;
+
+pub struct Outcome {
+    pub neutral: bool,
+    pub home_win: bool,
+    pub margin: Option<u32>,
+    pub date: Option<DateTime<Utc>>,
+}
+
+impl From<&Game> for Outcome {
+    fn from(value: &Game) -> Self {
+        Outcome {
+            neutral: value.neutral,
+            home_win: value.home_score > value.away_score,
+            margin: Some(value.home_score.abs_diff(value.away_score)),
+            date: Some(value.date),
+        }
+    }
+}
+
+pub trait Predictor {
+    fn predict(
+        &self, config: &Config, home: &Team, away: &Team, neutral: bool,
+        date: Option<DateTime<Utc>>,
+    ) -> f64;
+
+    fn update(&self, config: &Config, home: &mut Team, away: &mut Team, outcome: &Outcome);
+}
+
+pub struct Elo;
+
+impl Predictor for Elo {
+    fn predict(
+        &self, config: &Config, home: &Team, away: &Team, neutral: bool, _: Option<DateTime<Utc>>,
+    ) -> f64 {
+        utils::expected(home.elo + config.home_bonus(neutral), away.elo)
+    }
+
+    fn update(&self, config: &Config, home: &mut Team, away: &mut Team, outcome: &Outcome) {
+        let expected = self.predict(config, home, away, outcome.neutral, outcome.date);
+        let actual = if outcome.home_win { 1.0 } else { 0.0 };
+        let mut change = config.k_factor * (actual - expected);
+        if let (true, Some(margin)) = (config.margin_of_victory, outcome.margin) {
+            let home_elo_diff = home.elo + config.home_bonus(outcome.neutral) - away.elo;
+            let winner_elo_diff = if outcome.home_win { home_elo_diff } else { -home_elo_diff };
+            change *= utils::margin_multiplier(margin, winner_elo_diff);
+        }
+        home.elo += change;
+        away.elo -= change;
+    }
+}
+
+pub struct BradleyTerry;
+
+impl Predictor for BradleyTerry {
+    fn predict(
+        &self, config: &Config, home: &Team, away: &Team, neutral: bool,
+        date: Option<DateTime<Utc>>,
+    ) -> f64 {
+        Elo.predict(config, home, away, neutral, date)
+    }
+
+    fn update(&self, _: &Config, _: &mut Team, _: &mut Team, _: &Outcome) {}
+}
+
+pub struct Srs;
+
+impl Predictor for Srs {
+    fn predict(
+        &self, config: &Config, home: &Team, away: &Team, neutral: bool, _: Option<DateTime<Utc>>,
+    ) -> f64 {
+        let srs = |team: &Team| team.points.map_or(0.0, |x| x.srs);
+        let home_points = if neutral { 0.0 } else { config.srs.home_points };
+        utils::normal_cdf((srs(home) + home_points - srs(away)) / config.srs.margin_deviation)
+    }
+
+    fn update(&self, _: &Config, _: &mut Team, _: &mut Team, _: &Outcome) {}
+}
+
+pub struct Pythagorean;
+
+impl Predictor for Pythagorean {
+    fn predict(
+        &self, config: &Config, home: &Team, away: &Team, neutral: bool, _: Option<DateTime<Utc>>,
+    ) -> f64 {
+        let logit = |team: &Team| {
+            let percentage = team.points.map_or(0.5, |x| x.pythagorean).clamp(0.01, 0.99);
+            (percentage / (1.0 - percentage)).ln()
+        };
+        let diff = logit(home) - logit(away) + config.home_bonus(neutral) / SCALE;
+        1.0 / (1.0 + (-diff).exp())
+    }
+
+    fn update(&self, _: &Config, _: &mut Team, _: &mut Team, _: &Outcome) {}
+}
+
+pub struct Glicko;
+
+struct Step {
+    change: f64,
+    phi: f64,
+    volatility: f64,
+}
+
+impl Step {
+    fn apply(&self, team: &mut Team, state: GlickoState, date: Option<DateTime<Utc>>) {
+        team.elo += self.change;
+        team.glicko = Some(GlickoState {
+            rd: self.phi * SCALE,
+            volatility: self.volatility,
+            last_played: date.or(state.last_played),
+        });
+    }
+}
+
+fn g(phi: f64) -> f64 { 1.0 / (1.0 + 3.0 * phi.powi(2) / PI.powi(2)).sqrt() }
+
+fn e(mu: f64, opponent_mu: f64, opponent_phi: f64) -> f64 {
+    1.0 / (1.0 + (-g(opponent_phi) * (mu - opponent_mu)).exp())
+}
+
+fn volatility(tau: f64, phi: f64, sigma: f64, v: f64, delta: f64) -> f64 {
+    let a = sigma.powi(2).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta.powi(2) - phi.powi(2) - v - ex) / (2.0 * (phi.powi(2) + v + ex).powi(2))
+            - (x - a) / tau.powi(2)
+    };
+    let mut low = a;
+    let mut high = if delta.powi(2) > phi.powi(2) + v {
+        (delta.powi(2) - phi.powi(2) - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * tau) < 0.0 {
+            k += 1.0;
+        }
+        a - k * tau
+    };
+    let (mut f_low, mut f_high) = (f(low), f(high));
+    while (high - low).abs() > EPSILON {
+        let next = low + (low - high) * f_low / (f_high - f_low);
+        let f_next = f(next);
+        if f_next * f_high <= 0.0 {
+            low = high;
+            f_low = f_high;
+        } else {
+            f_low /= 2.0;
+        }
+        high = next;
+        f_high = f_next;
+    }
+    (low / 2.0).exp()
+}
+
+/// Rating period update against `opponents` given as `(mu, phi, score)`.
+fn step(tau: f64, mu: f64, phi: f64, sigma: f64, opponents: &[(f64, f64, f64)]) -> Step {
+    let (information, improvement) =
+        opponents.iter().fold((0.0, 0.0), |(information, improvement), &(x_mu, x_phi, score)| {
+            let expected = e(mu, x_mu, x_phi);
+            (
+                information + g(x_phi).powi(2) * expected * (1.0 - expected),
+                improvement + g(x_phi) * (score - expected),
+            )
+        });
+    let v = 1.0 / information;
+    let volatility = volatility(tau, phi, sigma, v, v * improvement);
+    let phi_star = (phi.powi(2) + volatility.powi(2)).sqrt();
+    let phi = 1.0 / (1.0 / phi_star.powi(2) + 1.0 / v).sqrt();
+    let change = SCALE * phi.powi(2) * improvement;
+    Step { change, phi, volatility }
+}
+
+impl GlickoState {
+    fn new(config: &GlickoConfig) -> Self {
+        GlickoState {
+            rd: config.initial_rd,
+            volatility: config.initial_volatility,
+            last_played: None,
+        }
+    }
+
+    fn phi(&self, config: &GlickoConfig, date: Option<DateTime<Utc>>) -> f64 {
+        let idle = match (self.last_played, date) {
+            (Some(last), Some(date)) => (utils::days(last, date) - 1.0).max(0.0),
+            _ => 0.0,
+        };
+        let phi = (self.rd / SCALE).powi(2) + idle * self.volatility.powi(2);
+        phi.sqrt().min(config.initial_rd / SCALE)
+    }
+}
+
+impl Glicko {
+    fn state(config: &Config, team: &Team) -> GlickoState {
+        team.glicko.unwrap_or_else(|| GlickoState::new(&config.glicko))
+    }
+}
+
+impl Predictor for Glicko {
+    fn predict(
+        &self, config: &Config, home: &Team, away: &Team, neutral: bool,
+        date: Option<DateTime<Utc>>,
+    ) -> f64 {
+        let home_phi = Glicko::state(config, home).phi(&config.glicko, date);
+        let away_phi = Glicko::state(config, away).phi(&config.glicko, date);
+        let phi = (home_phi.powi(2) + away_phi.powi(2)).sqrt();
+        e((home.elo + config.home_bonus(neutral)) / SCALE, away.elo / SCALE, phi)
+    }
+
+    fn update(&self, config: &Config, home: &mut Team, away: &mut Team, outcome: &Outcome) {
+        let (home_state, away_state) = (Glicko::state(config, home), Glicko::state(config, away));
+        let home_phi = home_state.phi(&config.glicko, outcome.date);
+        let away_phi = away_state.phi(&config.glicko, outcome.date);
+        let home_mu = (home.elo + config.home_bonus(outcome.neutral)) / SCALE;
+        let away_mu = away.elo / SCALE;
+        let score = if outcome.home_win { 1.0 } else { 0.0 };
+        let tau = config.glicko.tau;
+        let home_step =
+            step(tau, home_mu, home_phi, home_state.volatility, &[(away_mu, away_phi, score)]);
+        let away_step = step(
+            tau,
+            away_mu,
+            away_phi,
+            away_state.volatility,
+            &[(home_mu, home_phi, 1.0 - score)],
+        );
+        home_step.apply(home, home_state, outcome.date);
+        away_step.apply(away, away_state, outcome.date);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::test_utils::team;
+
+    #[test]
+    fn step_matches_glickman_example() {
+        // example from Glickman's "Example of the Glicko-2 system"
+        let opponents = [(1400.0, 30.0, 1.0), (1550.0, 100.0, 0.0), (1700.0, 300.0, 0.0)]
+            .map(|(rating, rd, score)| ((rating - 1500.0) / SCALE, rd / SCALE, score));
+        let step = step(0.5, 0.0, 200.0 / SCALE, 0.06, &opponents);
+        assert!((step.change + 35.94).abs() < 0.01, "{}", step.change);
+        assert!((step.phi * SCALE - 151.52).abs() < 0.01, "{}", step.phi * SCALE);
+        assert!((step.volatility - 0.059_99).abs() < 0.000_01, "{}", step.volatility);
+    }
+
+    #[test]
+    fn prediction_is_less_certain_after_idle_time() {
+        let config = Config::default();
+        let last_played = Utc::now();
+        let state = GlickoState { rd: 50.0, volatility: 0.06, last_played: Some(last_played) };
+        let home = Team { elo: 1100.0, glicko: Some(state), ..team("BOS") };
+        let away = Team { glicko: Some(state), ..team("MIA") };
+        let fresh = Glicko.predict(&config, &home, &away, true, Some(last_played));
+        let idle = last_played + Duration::days(200);
+        let rusty = Glicko.predict(&config, &home, &away, true, Some(idle));
+        assert!(0.5 < rusty && rusty < fresh, "{rusty} {fresh}");
+    }
+}
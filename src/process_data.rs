@@ -1,33 +1,282 @@
 use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-use crate::types::{Game, Team};
-use crate::{display, utils};
+use crate::config::{Config, RatingModel};
+use crate::predictor::Outcome;
+use crate::types::{Game, RatingChange, Team};
+use crate::utils::{self, Paths};
+use crate::{bradley_terry, display, point_ratings};
 
-fn process(game: &Game, teams: &[RefCell<Team>], print: &str) {
+pub fn process(game: &Game, teams: &[RefCell<Team>], config: &Config, print: &str) -> RatingChange {
     let mut home = teams[game.home_team_idx].borrow_mut();
     let mut away = teams[game.away_team_idx].borrow_mut();
-    let expected = utils::expected(home.elo, away.elo);
-    let actual = if game.home_score > game.away_score { 1.0 } else { 0.0 };
-    let change = 32.0 * (actual - expected);
+    let predictor = config.predictor();
+    let expected = predictor.predict(config, &home, &away, game.neutral, Some(game.date));
+    let outcome = Outcome::from(game);
     let home_old = home.elo;
     let away_old = away.elo;
-    home.elo += change;
-    away.elo -= change;
+    predictor.update(config, &mut home, &mut away, &outcome);
     if print == "*" || home.name == print || away.name == print {
-        display::print_game_info(game, &home, &away, expected, home_old, away_old, change);
+        display::print_game_info(game, &home, &away, expected, home_old, away_old);
     }
+    RatingChange::new(game, &home, &away, outcome.home_win, expected, home_old, away_old)
 }
 
-pub fn process_games() -> Result<()> {
-    let teams = utils::load_teams_cell("files/teams.json")?;
-    let games = utils::load_games("files/prev_games.json")?;
-    let team = utils::ask_for_team_cell(&teams);
-    for game in &games {
-        process(game, &teams, &team);
+pub fn regress_to_mean(teams: &[RefCell<Team>], fraction: f64) {
+    let (total, count) =
+        teams.iter().fold((0.0, 0.0), |(total, count), x| (total + x.borrow().elo, count + 1.0));
+    let mean = total / count;
+    for team in teams {
+        let mut team = team.borrow_mut();
+        team.elo -= fraction * (team.elo - mean);
     }
+}
+
+pub fn reset_ratings(teams: &mut [Team], config: &Config) {
+    for team in teams {
+        team.elo = config.initial_elo;
+        team.glicko = None;
+    }
+}
+
+/// Processes `games` in order, calling `new_season` with the index of the first game of every
+/// season after regressing the ratings for it.
+pub fn replay<S, F>(
+    games: &[Game], teams: &[RefCell<Team>], config: &mut Config, print: &str, mut new_season: S,
+    mut record: F,
+) where
+    S: FnMut(&mut Config, usize),
+    F: FnMut(RatingChange),
+{
+    let mut season = None;
+    for (i, game) in games.iter().enumerate() {
+        if season != Some(game.season) {
+            if season.is_some() {
+                regress_to_mean(teams, config.season_regression);
+            }
+            new_season(config, i);
+            season = Some(game.season);
+        }
+        record(process(game, teams, config, print));
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Exclusion {
+    NotFinal,
+    NoResult,
+}
+
+impl Exclusion {
+    fn check(game: &Game) -> Option<Self> {
+        if !game.is_final() {
+            Some(Exclusion::NotFinal)
+        } else if game.home_score == game.away_score {
+            Some(Exclusion::NoResult)
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    pub fn reason(self) -> &'static str {
+        match self {
+            Exclusion::NotFinal => "not final",
+            Exclusion::NoResult => "no winner",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessedAt {
+    pub date: DateTime<Utc>,
+    pub season: u32,
+    /// Ids of the games already reflected in the processed ratings.
+    #[serde(default)]
+    pub applied: BTreeSet<u32>,
+}
+
+impl ProcessedAt {
+    fn save(games: &[Game], mut applied: BTreeSet<u32>, path: &str) -> Result<()> {
+        let Some(last) = games.last() else {
+            return Ok(());
+        };
+        applied.extend(games.iter().map(|x| x.id).filter(|&x| x != 0));
+        utils::write_json(&ProcessedAt { date: last.date, season: last.season, applied }, path)
+    }
+}
+
+pub type Season = (Vec<RefCell<Team>>, Vec<Game>, BTreeSet<u32>);
+
+pub fn load_season(paths: &Paths, config: &Config) -> Result<Season> {
+    let teams = utils::load_teams(&paths.processed)?;
+    let games = utils::load_games(&paths.games, &teams)?;
+    let teams: Vec<_> = teams.into_iter().map(RefCell::new).collect();
+    if !Path::new(&paths.processed_at).exists() {
+        return Ok((teams, games, BTreeSet::new()));
+    }
+    let processed_at: ProcessedAt = utils::read_json(&paths.processed_at)?;
+    if let Some(season) = games.iter().map(|x| x.season).filter(|&x| x > processed_at.season).min()
+    {
+        regress_to_mean(&teams, config.season_regression);
+        println!("Regressed ratings toward the mean for season {season}");
+    }
+    Ok((teams, games, processed_at.applied))
+}
+
+#[must_use]
+pub fn valid_games(all: Vec<Game>, teams: &[Team]) -> Vec<Game> {
+    let mut games = Vec::new();
+    let mut excluded = Vec::new();
+    for game in all {
+        match Exclusion::check(&game) {
+            Some(exclusion) => excluded.push((game, exclusion)),
+            None => games.push(game),
+        }
+    }
+    display::display_excluded(&excluded, teams, games.len());
+    games
+}
+
+fn load_config(path: &str, games: &[Game]) -> Result<Config> {
+    let mut config = Config::load(path)?;
+    if config.fit_home_advantage {
+        config.fit_home_advantage(games);
+        println!("Fitted home advantage: {:.2}", config.home_advantage);
+    }
+    Ok(config)
+}
+
+fn log_loss(teams: &[Team], games: &[Game], mut config: Config) -> f64 {
+    let teams: Vec<_> = teams.iter().cloned().map(RefCell::new).collect();
+    let mut total = 0.0;
+    let mut count = 0.0;
+    replay(games, &teams, &mut config, "", |_, _| {}, |change| {
+        let probability = if change.home_win { change.expected } else { 1.0 - change.expected };
+        total -= probability.ln();
+        count += 1.0;
+    });
+    total / count
+}
+
+pub fn fit_k_factor(paths: &Paths) -> Result<()> {
+    let mut teams = utils::load_teams(&paths.teams)?;
+    let games = valid_games(utils::load_games(&paths.previous_games, &teams)?, &teams);
+    let mut config = load_config(&paths.config, &games)?;
+    reset_ratings(&mut teams, &config);
+    let (k_factor, loss) = (1..=60)
+        .map(|k| {
+            let candidate = Config { k_factor: f64::from(k), ..config.clone() };
+            (candidate.k_factor, log_loss(&teams, &games, candidate))
+        })
+        .min_by(|x, y| x.1.total_cmp(&y.1))
+        .unwrap();
+    println!("Fitted k-factor: {k_factor} (log loss {loss:.4})");
+    config.k_factor = k_factor;
+    config.save(&paths.config)?;
+    Ok(())
+}
+
+fn played(mut games: Vec<Game>) -> Vec<Game> {
+    games.retain(|x| Exclusion::check(x).is_none());
+    games
+}
+
+pub fn load_current_games(path: &str, teams: &[Team]) -> Result<Vec<Game>> {
+    if Path::new(path).exists() {
+        Ok(played(utils::load_games(path, teams)?))
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+pub fn process_games(paths: &Paths, team: Option<&str>) -> Result<()> {
+    let mut teams = utils::load_teams(&paths.teams)?;
+    let games = valid_games(utils::load_games(&paths.previous_games, &teams)?, &teams);
+    let current = load_current_games(&paths.games, &teams)?;
+    let mut config = load_config(&paths.config, &games)?;
+    reset_ratings(&mut teams, &config);
+    let recent = point_ratings::latest_season(games.iter().chain(&current));
+    point_ratings::fit(&mut teams, &recent, &mut config)?;
+    if config.rating_model == RatingModel::BradleyTerry {
+        return fit_bradley_terry(paths, teams, games, current, config);
+    }
+    config.save(&paths.config)?;
+    let team = team.map_or_else(|| utils::ask_for_team(&teams), str::to_uppercase);
+    let teams: Vec<_> = teams.into_iter().map(RefCell::new).collect();
+    let mut history = Vec::new();
+    replay(&games, &teams, &mut config, &team, |_, _| {}, |x| history.push(x));
+    let teams: Vec<_> = teams.into_iter().map(RefCell::into_inner).collect();
+    utils::write_json(&teams, &paths.processed)?;
+    utils::write_json(&history, &paths.history)?;
+    ProcessedAt::save(&games, BTreeSet::new(), &paths.processed_at)?;
+    Ok(())
+}
+
+fn fit_bradley_terry(
+    paths: &Paths, mut teams: Vec<Team>, mut games: Vec<Game>, current: Vec<Game>,
+    mut config: Config,
+) -> Result<()> {
+    games.extend(current);
+    games.sort_by_key(|x| x.date);
+    bradley_terry::fit(&mut teams, &games, &mut config);
+    println!("Fitted Bradley-Terry ratings, home advantage: {:.2}", config.home_advantage);
+    config.save(&paths.config)?;
+    utils::write_json(&teams, &paths.processed)?;
+    // batch fitted ratings have no per-game history, don't leave the last elo run behind
+    utils::write_json(&Vec::<RatingChange>::new(), &paths.history)?;
+    ProcessedAt::save(&games, BTreeSet::new(), &paths.processed_at)?;
+    Ok(())
+}
+
+pub fn update_ratings(paths: &Paths, team: Option<&str>) -> Result<()> {
+    let mut config = Config::load(&paths.config)?;
+    if config.rating_model == RatingModel::BradleyTerry {
+        return Err(anyhow!("batch fitted ratings can't be updated, process games again"));
+    }
+    if !Path::new(&paths.processed_at).exists() {
+        return Err(anyhow!("{} not found, process games first", paths.processed_at));
+    }
+    let processed_at: ProcessedAt = utils::read_json(&paths.processed_at)?;
+    if processed_at.applied.is_empty() {
+        return Err(anyhow!(
+            "{} doesn't list the applied games, process games again",
+            paths.processed_at
+        ));
+    }
+    let mut teams = utils::load_teams(&paths.processed)?;
+    let previous = valid_games(utils::load_games(&paths.previous_games, &teams)?, &teams);
+    let current = load_current_games(&paths.games, &teams)?;
+    if previous.iter().chain(&current).any(|x| x.id == 0) {
+        return Err(anyhow!("some games have no id, download them again"));
+    }
+    let recent = point_ratings::latest_season(previous.iter().chain(&current));
+    point_ratings::fit(&mut teams, &recent, &mut config)?;
+    config.save(&paths.config)?;
+    let mut seen = processed_at.applied.clone();
+    let mut games: Vec<_> =
+        previous.into_iter().chain(current).filter(|x| seen.insert(x.id)).collect();
+    games.sort_by_key(|x| x.date);
+    let team = team.map_or_else(|| utils::ask_for_team(&teams), str::to_uppercase);
+    let teams: Vec<_> = teams.into_iter().map(RefCell::new).collect();
+    if games.first().is_some_and(|x| x.season != processed_at.season) {
+        regress_to_mean(&teams, config.season_regression);
+    }
+    let mut history: Vec<RatingChange> = if Path::new(&paths.history).exists() {
+        utils::read_json(&paths.history)?
+    } else {
+        Vec::new()
+    };
+    replay(&games, &teams, &mut config, &team, |_, _| {}, |x| history.push(x));
     let teams: Vec<_> = teams.into_iter().map(RefCell::into_inner).collect();
-    utils::write_json(&teams, "files/processed.json")?;
+    utils::write_json(&teams, &paths.processed)?;
+    utils::write_json(&history, &paths.history)?;
+    ProcessedAt::save(&games, processed_at.applied, &paths.processed_at)?;
+    println!("Applied {} new games", games.len());
     Ok(())
 }
@@ -0,0 +1,344 @@
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{NaiveDate, Utc};
+use reqwest::header::{AUTHORIZATION, RETRY_AFTER};
+use reqwest::{Client, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use crate::get_data::{GameData, TeamData};
+use crate::utils;
+
+pub trait Provider {
+    fn teams(&self) -> impl Future<Output = Result<Vec<TeamData>>>;
+
+    fn games(
+        &self, seasons: &[u32], since: Option<NaiveDate>,
+    ) -> impl Future<Output = Result<Vec<GameData>>>;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TeamResponse {
+    data: Vec<TeamData>,
+    meta: MetaData,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GameResponse {
+    data: Vec<GameData>,
+    meta: MetaData,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MetaData {
+    total_pages: u32,
+    current_page: u32,
+    next_page: Option<u32>,
+    per_page: u32,
+    total_count: u32,
+}
+
+pub const BALLDONTLIE_URL: &str = "https://www.balldontlie.io/api/v1";
+
+const MAX_RETRIES: u32 = 5;
+const TIMEOUT: Duration = Duration::from_secs(30);
+const BACKOFF: Duration = Duration::from_secs(1);
+
+pub struct BallDontLie {
+    client: Client,
+    base_url: String,
+    api_key: Option<String>,
+    cache: Option<String>,
+    backoff: Duration,
+}
+
+impl BallDontLie {
+    pub fn new(base_url: &str, api_key: Option<String>) -> Result<Self> {
+        let client = Client::builder().timeout(TIMEOUT).build()?;
+        let base_url = base_url.trim_end_matches('/').into();
+        Ok(BallDontLie { client, base_url, api_key, cache: None, backoff: BACKOFF })
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.client = Client::builder().timeout(timeout).build()?;
+        Ok(self)
+    }
+
+    #[must_use]
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    #[must_use]
+    pub fn with_cache(mut self, dir: String) -> Self {
+        self.cache = Some(dir);
+        self
+    }
+
+    async fn get_page(&self, url: Url, cached: Option<&str>) -> Result<GameResponse> {
+        if let Some(path) = cached {
+            if Path::new(path).exists() {
+                println!("Using cached page {path}");
+                return utils::read_json(path);
+            }
+        }
+        let response = self.get_json_response(url).await?;
+        if let Some(path) = cached {
+            utils::write_json(&response, path)?;
+        }
+        Ok(response)
+    }
+
+    async fn get_json_response<T>(&self, url: Url) -> Result<T>
+    where T: DeserializeOwned {
+        let mut attempt = 0;
+        loop {
+            let mut request = self.client.get(url.clone());
+            if let Some(api_key) = &self.api_key {
+                request = request.header(AUTHORIZATION, api_key);
+            }
+            let backoff = self.backoff * 2u32.pow(attempt);
+            let delay = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    return response
+                        .json()
+                        .await
+                        .with_context(|| format!("invalid response from {url}"));
+                },
+                Ok(response) => {
+                    let status = response.status();
+                    if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error()
+                        || attempt == MAX_RETRIES
+                    {
+                        return Err(anyhow!("request to {url} failed with status {status}"));
+                    }
+                    let retry_after = response
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|x| x.to_str().ok())
+                        .and_then(|x| x.trim().parse().ok())
+                        .map(Duration::from_secs);
+                    println!("Got status {status} from {url}");
+                    retry_after.unwrap_or(backoff)
+                },
+                Err(error)
+                    if attempt < MAX_RETRIES && (error.is_timeout() || error.is_connect()) =>
+                {
+                    println!("Request to {url} failed: {error}");
+                    backoff
+                },
+                Err(error) => {
+                    return Err(error).with_context(|| format!("request to {url} failed"))
+                },
+            };
+            attempt += 1;
+            println!("Retrying in {}s ({attempt}/{MAX_RETRIES})", delay.as_secs_f64());
+            sleep(delay).await;
+        }
+    }
+}
+
+impl Provider for BallDontLie {
+    async fn teams(&self) -> Result<Vec<TeamData>> {
+        let url = Url::parse(&format!("{}/teams", self.base_url))?;
+        let team_response: TeamResponse = self.get_json_response(url).await?;
+        println!("{:?}", team_response.meta);
+        Ok(team_response.data)
+    }
+
+    async fn games(&self, seasons: &[u32], since: Option<NaiveDate>) -> Result<Vec<GameData>> {
+        let url_base = format!("{}/games", self.base_url);
+        let mut query: Vec<(_, String)> = vec![("page", "0".into()), ("per_page", "100".into())];
+        query.extend(seasons.iter().map(|x| ("seasons[]", x.to_string())));
+        let mut key: Vec<_> = seasons.iter().map(u32::to_string).collect();
+        if let Some(since) = since {
+            query.push(("start_date", since.to_string()));
+            key.push(format!("since_{since}"));
+        }
+        // pages are only resumed on the day they were fetched, older ones may be out of date
+        let today = Utc::now().date_naive();
+        let key = format!("{today}_{}", key.join("_"));
+        if let Some(cache) = &self.cache {
+            fs::create_dir_all(cache)?;
+            clear_stale_pages(cache, &format!("games_{today}_"))?;
+        }
+        let mut pages = Vec::new();
+        let mut games = Vec::new();
+        loop {
+            let cached =
+                self.cache.as_ref().map(|x| format!("{x}/games_{key}_page_{}.json", query[0].1));
+            let url = Url::parse_with_params(&url_base, &query)?;
+            let game_response = self.get_page(url, cached.as_deref()).await?;
+            println!("{:?}", game_response.meta);
+            games.extend(game_response.data);
+            pages.extend(cached);
+            let Some(next) = game_response.meta.next_page else {
+                break;
+            };
+            query[0].1 = next.to_string();
+        }
+        for page in pages {
+            fs::remove_file(page)?;
+        }
+        Ok(games)
+    }
+}
+
+fn clear_stale_pages(cache: &str, current: &str) -> Result<()> {
+    for entry in fs::read_dir(cache)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|x| x.to_str()).unwrap_or_default();
+        if name.starts_with("games_") && !name.starts_with(current) {
+            println!("Removing stale cached page {}", path.display());
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+pub struct Fixtures {
+    dir: String,
+}
+
+impl Fixtures {
+    #[must_use]
+    pub fn new(dir: String) -> Self { Fixtures { dir } }
+}
+
+impl Provider for Fixtures {
+    async fn teams(&self) -> Result<Vec<TeamData>> {
+        utils::read_json(&format!("{}/teams.json", self.dir))
+    }
+
+    async fn games(&self, seasons: &[u32], _since: Option<NaiveDate>) -> Result<Vec<GameData>> {
+        let mut games = Vec::new();
+        for season in seasons {
+            let season: Vec<GameData> =
+                utils::read_json(&format!("{}/games_{season}.json", self.dir))?;
+            games.extend(season);
+        }
+        Ok(games)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{self, Receiver};
+    use std::{env, thread};
+
+    use serde_json::Value;
+
+    use super::*;
+
+    /// Serves `responses` in order, one per connection, and forwards every request head.
+    fn serve(responses: Vec<&'static str>) -> (String, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..n]);
+                }
+                sender.send(String::from_utf8_lossy(&request).into_owned()).unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 {response}\r\nContent-Type: application/json\r\nContent-Length: \
+                     2\r\nConnection: close\r\n\r\n{{}}"
+                )
+                .unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    fn provider(url: &str, backoff: Duration) -> BallDontLie {
+        BallDontLie::new(url, Some("secret".into()))
+            .unwrap()
+            .with_timeout(Duration::from_secs(5))
+            .unwrap()
+            .with_backoff(backoff)
+    }
+
+    async fn get(provider: &BallDontLie, url: &str) -> Result<Value> {
+        provider.get_json_response(Url::parse(&format!("{url}/games")).unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn retries_after_too_many_requests() {
+        let (url, requests) = serve(vec!["429 Too Many Requests\r\nRetry-After: 0", "200 OK"]);
+        // the backoff alone would wait an hour, only `Retry-After` finishes in time
+        let provider = provider(&url, Duration::from_secs(3600));
+        let response = tokio::time::timeout(Duration::from_secs(5), get(&provider, &url)).await;
+        assert!(response.expect("Retry-After was ignored").is_ok());
+        assert_eq!(requests.try_iter().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_until_success() {
+        let (url, requests) =
+            serve(vec!["500 Internal Server Error", "503 Service Unavailable", "200 OK"]);
+        let provider = provider(&url, Duration::ZERO);
+        assert!(get(&provider, &url).await.is_ok());
+        assert_eq!(requests.try_iter().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let responses = vec!["500 Internal Server Error"; MAX_RETRIES as usize + 2];
+        let (url, requests) = serve(responses);
+        let provider = provider(&url, Duration::ZERO);
+        let error = get(&provider, &url).await.unwrap_err().to_string();
+        assert!(error.contains("500"), "{error}");
+        assert!(error.contains(&format!("{url}/games")), "{error}");
+        assert_eq!(requests.try_iter().count(), MAX_RETRIES as usize + 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (url, requests) = serve(vec!["404 Not Found", "200 OK"]);
+        let provider = provider(&url, Duration::ZERO);
+        let error = get(&provider, &url).await.unwrap_err().to_string();
+        assert!(error.contains("404"), "{error}");
+        assert_eq!(requests.try_iter().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn sends_api_key() {
+        let (url, requests) = serve(vec!["200 OK"]);
+        let provider = provider(&url, Duration::ZERO);
+        assert!(get(&provider, &url).await.is_ok());
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.contains("authorization: secret"), "{request}");
+    }
+
+    #[tokio::test]
+    async fn resumes_todays_pages_and_clears_stale_ones() {
+        let cache = env::temp_dir().join(format!("nba-cache-{}", std::process::id()));
+        fs::create_dir_all(&cache).unwrap();
+        let page = r#"{"data":[],"meta":{"total_pages":1,"current_page":0,"next_page":null,"per_page":100,"total_count":0}}"#;
+        let stale = cache.join("games_2000-01-01_2023_page_0.json");
+        let today = cache.join(format!("games_{}_2023_page_0.json", Utc::now().date_naive()));
+        fs::write(&stale, page).unwrap();
+        fs::write(&today, page).unwrap();
+
+        let (url, requests) = serve(vec![]);
+        let provider = provider(&url, Duration::ZERO).with_cache(cache.to_str().unwrap().into());
+        assert!(provider.games(&[2023], None).await.unwrap().is_empty());
+        assert_eq!(requests.try_iter().count(), 0);
+        assert!(!stale.exists());
+        fs::remove_dir_all(cache).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::types::{Game, Team, TeamId};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameRecord {
+    #[serde(default)]
+    pub id: u32,
+    #[serde(deserialize_with = "date")]
+    pub date: DateTime<Utc>,
+    #[serde(default)]
+    pub season: u32,
+    pub home_team: String,
+    pub away_team: String,
+    pub home_score: u32,
+    pub away_score: u32,
+    #[serde(default)]
+    pub neutral: bool,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub period: u32,
+    #[serde(default)]
+    pub postseason: bool,
+}
+
+fn date<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where D: Deserializer<'de> {
+    let value = String::deserialize(deserializer)?;
+    value
+        .parse()
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                .map(|x| x.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+        })
+        .map_err(|_| de::Error::custom(format!("invalid date {value}, expected e.g. 2023-10-24")))
+}
+
+impl GameRecord {
+    pub fn new(game: &Game, teams: &[Team]) -> Result<Self> {
+        let name = |idx: usize| {
+            teams
+                .get(idx)
+                .map(|x| x.name.clone())
+                .ok_or_else(|| anyhow!("no team with index {idx}"))
+        };
+        Ok(GameRecord {
+            id: game.id,
+            date: game.date,
+            season: game.season,
+            home_team: name(game.home_team_idx)?,
+            away_team: name(game.away_team_idx)?,
+            home_score: game.home_score,
+            away_score: game.away_score,
+            neutral: game.neutral,
+            status: Some(game.status.clone()),
+            period: game.period,
+            postseason: game.postseason,
+        })
+    }
+}
+
+pub struct TeamRegistry {
+    by_id: HashMap<TeamId, usize>,
+    by_name: HashMap<String, usize>,
+}
+
+impl TeamRegistry {
+    pub fn new(teams: &[Team]) -> Result<Self> {
+        let mut registry = TeamRegistry { by_id: HashMap::new(), by_name: HashMap::new() };
+        for (i, team) in teams.iter().enumerate() {
+            if let Some(id) = team.id {
+                if registry.by_id.insert(id, i).is_some() {
+                    return Err(anyhow!("duplicate team id {}", id.0));
+                }
+            }
+            if registry.by_name.insert(team.name.clone(), i).is_some() {
+                return Err(anyhow!("duplicate team abbreviation {}", team.name));
+            }
+        }
+        Ok(registry)
+    }
+
+    #[must_use]
+    pub fn by_id(&self, id: TeamId) -> Option<usize> { self.by_id.get(&id).copied() }
+
+    #[must_use]
+    pub fn by_name(&self, name: &str) -> Option<usize> { self.by_name.get(name).copied() }
+
+    pub fn name(&self, name: &str) -> Result<usize> {
+        self.by_name(name).ok_or_else(|| anyhow!("unknown team abbreviation {name}"))
+    }
+
+    pub fn resolve(&self, id: TeamId, name: &str) -> Result<usize> {
+        self.by_id(id)
+            .or_else(|| self.by_name(name))
+            .ok_or_else(|| anyhow!("unknown team {name} with id {}", id.0))
+    }
+
+    pub fn game(&self, record: GameRecord) -> Result<Game> {
+        let status = match record.status.filter(|x| !x.is_empty()) {
+            Some(status) => status,
+            None if record.home_score + record.away_score > 0 => "Final".into(),
+            None => String::new(),
+        };
+        Ok(Game {
+            id: record.id,
+            date: record.date,
+            season: record.season,
+            home_team_idx: self.name(&record.home_team)?,
+            away_team_idx: self.name(&record.away_team)?,
+            home_score: record.home_score,
+            away_score: record.away_score,
+            neutral: record.neutral,
+            status,
+            period: record.period,
+            postseason: record.postseason,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_plain_dates() {
+        let csv = "date,home_team,away_team,home_score,away_score\n2023-10-24,BOS,LAL,108,104\n";
+        let record: GameRecord =
+            csv::Reader::from_reader(csv.as_bytes()).deserialize().next().unwrap().unwrap();
+        assert_eq!(record.date, "2023-10-24T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+    }
+}
@@ -1,55 +1,176 @@
 use core::array;
 use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::f64::consts::PI;
+use std::path::Path;
 
 use anyhow::Result;
-use rand::random;
+use rand::rngs::StdRng;
+use rand::{random, Rng, SeedableRng};
 
-use crate::types::{Conference, Game, Team};
-use crate::{display, utils};
+use crate::config::{Config, SeriesFormat};
+use crate::predictor::Outcome;
+use crate::standings::Standings;
+use crate::types::{Conference, Game, RatingChange, Team};
+use crate::utils::{self, Paths};
+use crate::{display, process_data};
 
-fn partition(mut teams: Vec<Team>) -> (Vec<Team>, Vec<Team>) {
-    teams.sort_by_key(|x| x.season_loses);
-    teams.into_iter().partition(|x| matches!(x.conference, Conference::West))
+#[must_use]
+pub fn partition(teams: Vec<Team>, rng: &mut StdRng) -> (Vec<Team>, Vec<Team>) {
+    let standings = Standings::new(&teams.iter().collect::<Vec<_>>(), rng);
+    let (west, east) = teams.into_iter().partition(|x| matches!(x.conference, Conference::West));
+    (standings.rank(west, rng), standings.rank(east, rng))
+}
+
+fn count_win(home: &mut Team, away: &mut Team, home_win: bool, home_margin: i64) {
+    home.point_differential += home_margin;
+    away.point_differential -= home_margin;
+    let (winner, loser) = if home_win { (home, away) } else { (away, home) };
+    winner.season_wins += 1;
+    loser.season_loses += 1;
+    winner.head_to_head.entry(loser.name.clone()).or_default().wins += 1;
+    loser.head_to_head.entry(winner.name.clone()).or_default().loses += 1;
+}
+
+pub struct Score {
+    pub home: u32,
+    pub away: u32,
+    pub periods: u32,
+}
+
+impl Score {
+    fn home_win(&self) -> bool { self.home > self.away }
+
+    fn home_margin(&self) -> i64 { i64::from(self.home) - i64::from(self.away) }
+}
+
+fn logistic_scale(deviation: f64) -> f64 { deviation * 3f64.sqrt() / PI }
+
+fn logistic(rng: &mut StdRng, mean: f64, deviation: f64) -> f64 {
+    let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+    mean + logistic_scale(deviation) * (u / (1.0 - u)).ln()
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn round(value: f64) -> i64 { value.round() as i64 }
+
+fn expected_points(config: &Config, team: &Team, opponent: &Team) -> f64 {
+    let offense = team.points.map_or(0.0, |x| x.offense);
+    let defense = opponent.points.map_or(0.0, |x| x.defense);
+    config.srs.average_points + offense + defense
+}
+
+struct ScoreModel {
+    margin_mean: f64,
+    total_mean: f64,
+    margin_deviation: f64,
+    total_deviation: f64,
+}
+
+impl ScoreModel {
+    fn new(config: &Config, home: &Team, away: &Team, expected: f64) -> Self {
+        let expected = expected.clamp(0.001, 0.999);
+        ScoreModel {
+            margin_mean: logistic_scale(config.srs.margin_deviation)
+                * (expected / (1.0 - expected)).ln(),
+            total_mean: expected_points(config, home, away) + expected_points(config, away, home),
+            margin_deviation: config.srs.margin_deviation,
+            total_deviation: config.srs.total_deviation,
+        }
+    }
+
+    /// Draws home and away points for a period lasting `length` of regulation.
+    fn period(&self, length: f64, rng: &mut StdRng) -> (i64, i64) {
+        let deviation = length.sqrt();
+        let margin =
+            round(logistic(rng, self.margin_mean * length, self.margin_deviation * deviation));
+        let total =
+            round(logistic(rng, self.total_mean * length, self.total_deviation * deviation));
+        let total = total.max(margin.abs());
+        let total = total + (total + margin).rem_euclid(2);
+        (i64::midpoint(total, margin), i64::midpoint(total, -margin))
+    }
+}
+
+fn simulate_score(
+    config: &Config, home: &Team, away: &Team, expected: f64, rng: &mut StdRng,
+) -> Score {
+    let model = ScoreModel::new(config, home, away, expected);
+    let (mut home_points, mut away_points, mut periods) = (0, 0, 4);
+    let mut length = 1.0;
+    loop {
+        let (home, away) = model.period(length, rng);
+        home_points += home;
+        away_points += away;
+        if home_points != away_points {
+            break;
+        }
+        periods += 1;
+        length = 5.0 / 48.0;
+    }
+    let points = |x: i64| u32::try_from(x).unwrap_or_default();
+    Score { home: points(home_points), away: points(away_points), periods }
 }
 
 fn simulate_from_teams(
-    name: &str, home: &mut Team, away: &mut Team, count_wins: bool, print: &str,
-) -> bool {
-    let expected = utils::expected(home.elo, away.elo);
-    let (actual, home_win) = if random::<f64>() < expected { (1.0, true) } else { (0.0, false) };
-    let change = 32.0 * (actual - expected);
+    name: &str, home: &mut Team, away: &mut Team, game: Option<&Game>, config: &Config,
+    print: &str, rng: &mut StdRng,
+) -> (Score, f64) {
+    let predictor = config.predictor();
+    let neutral = game.is_some_and(|x| x.neutral);
+    let expected = predictor.predict(config, home, away, neutral, game.map(|x| x.date));
+    let score = simulate_score(config, home, away, expected, rng);
     let home_old = home.elo;
     let away_old = away.elo;
-    home.elo += change;
-    away.elo -= change;
-    if count_wins {
-        if home_win {
-            home.season_wins += 1;
-            away.season_loses += 1;
-        } else {
-            home.season_loses += 1;
-            away.season_wins += 1;
-        }
-    }
+    let outcome = Outcome {
+        neutral,
+        home_win: score.home_win(),
+        margin: Some(score.home.abs_diff(score.away)),
+        date: game.map(|x| x.date),
+    };
+    predictor.update(config, home, away, &outcome);
     if print == "*" || home.name == print || away.name == print {
-        display::print_simulated_game_info(
-            name, home, away, home_win, expected, home_old, away_old, change,
-        );
+        display::print_simulated_game_info(name, home, away, &score, expected, home_old, away_old);
     }
-    home_win
+    (score, expected)
 }
 
-fn simulate_from_game(game: &Game, teams: &[RefCell<Team>], print: &str) {
+fn simulate_from_game(
+    game: &Game, teams: &[RefCell<Team>], applied: &BTreeSet<u32>, config: &Config, print: &str,
+    rng: &mut StdRng,
+) -> Option<RatingChange> {
+    if game.is_final() {
+        // games applied by `process --update` are already in the ratings and history
+        let change = (!applied.contains(&game.id))
+            .then(|| process_data::process(game, teams, config, print));
+        if !game.postseason {
+            let mut home = teams[game.home_team_idx].borrow_mut();
+            let mut away = teams[game.away_team_idx].borrow_mut();
+            let home_margin = i64::from(game.home_score) - i64::from(game.away_score);
+            count_win(&mut home, &mut away, home_margin > 0, home_margin);
+        }
+        return change;
+    }
+    if game.postseason {
+        return None;
+    }
     let name = game.date.to_string();
     let mut home = teams[game.home_team_idx].borrow_mut();
     let mut away = teams[game.away_team_idx].borrow_mut();
-    simulate_from_teams(&name, &mut home, &mut away, true, print);
+    let (home_old, away_old) = (home.elo, away.elo);
+    let (score, expected) =
+        simulate_from_teams(&name, &mut home, &mut away, Some(game), config, print, rng);
+    let home_win = score.home_win();
+    count_win(&mut home, &mut away, home_win, score.home_margin());
+    let change = RatingChange::new(game, &home, &away, home_win, expected, home_old, away_old);
+    Some(RatingChange { home_score: score.home, away_score: score.away, ..change })
 }
 
 fn simulate_with_info(
-    name: &str, mut home: Team, mut away: Team, print: &str,
+    name: &str, mut home: Team, mut away: Team, config: &Config, print: &str, rng: &mut StdRng,
 ) -> (Team, Team, GameData) {
-    let result = simulate_from_teams(name, &mut home, &mut away, false, print);
+    let (score, _) = simulate_from_teams(name, &mut home, &mut away, None, config, print, rng);
+    let result = score.home_win();
     let data = GameData::new(&home, &away, result);
     if result {
         (home, away, data)
@@ -58,32 +179,80 @@ fn simulate_with_info(
     }
 }
 
-pub fn simulate_season() -> Result<()> {
-    let teams = utils::load_teams_cell("files/processed.json")?;
-    let games = utils::load_games("files/games.json")?;
-    display::display_by_elo(&teams);
-    let team = utils::ask_for_team_cell(&teams);
-    for game in &games {
-        simulate_from_game(game, &teams, &team);
+pub fn play_season<F>(
+    teams: &[RefCell<Team>], games: &[Game], applied: &BTreeSet<u32>, config: &Config, print: &str,
+    rng: &mut StdRng, mut record: F,
+) where
+    F: FnMut(RatingChange),
+{
+    for game in games {
+        if let Some(change) = simulate_from_game(game, teams, applied, config, print, rng) {
+            record(change);
+        }
     }
+}
+
+pub fn simulate_season(paths: &Paths, team: Option<&str>, seed: Option<u64>) -> Result<()> {
+    let config = Config::load(&paths.config)?;
+    let (teams, games, applied) = process_data::load_season(paths, &config)?;
+    display::display_by_elo(&teams);
+    let team = team.map_or_else(|| utils::ask_for_team_cell(&teams), str::to_uppercase);
+    let seed = seed.unwrap_or_else(random);
+    let mut rng = utils::seeded_rng(Some(seed));
+    let mut history = Vec::new();
+    play_season(&teams, &games, &applied, &config, &team, &mut rng, |x| history.push(x));
     let teams: Vec<_> = teams.into_iter().map(RefCell::into_inner).collect();
-    utils::write_json(&teams, "files/after_season.json")?;
+    utils::write_json(&teams, &paths.after_season)?;
+    utils::write_json(&history, &paths.season_history)?;
+    utils::write_json(&seed, &paths.after_season_seed())?;
     Ok(())
 }
 
-pub fn simulate_postseason() -> Result<()> {
-    let teams = utils::load_teams("files/after_season.json")?;
-    let team = utils::ask_for_team(&teams);
-    let (west, east) = partition(teams);
+pub fn standings_rng(paths: &Paths) -> Result<StdRng> {
+    let path = paths.after_season_seed();
+    let seed = if Path::new(&path).exists() {
+        utils::read_json(&path)?
+    } else {
+        0
+    };
+    Ok(StdRng::seed_from_u64(seed))
+}
+
+pub fn simulate_postseason(paths: &Paths, team: Option<&str>, seed: Option<u64>) -> Result<()> {
+    let teams = utils::load_teams(&paths.after_season)?;
+    let config = Config::load(&paths.config)?;
+    let team = team.map_or_else(|| utils::ask_for_team(&teams), str::to_uppercase);
+    let (west, east) = partition(teams, &mut standings_rng(paths)?);
+    let mut rng = utils::seeded_rng(seed);
     display::display_by_wins(&west, &east);
+    let data = play_postseason(west, east, &config, &team, &mut rng);
+    display::display_ladder(&data.west, &data.east, &data.finals, &data.winner)?;
+    Ok(())
+}
+
+#[must_use]
+pub fn play_postseason(
+    west: Vec<Team>, east: Vec<Team>, config: &Config, print: &str, rng: &mut StdRng,
+) -> PostseasonData {
+    let standings = Standings::new(&west.iter().chain(&east).collect::<Vec<_>>(), rng);
     let west = ConferenceBracket::new("West".into(), west);
     let east = ConferenceBracket::new("East".into(), east);
-    let (west_winner, west_data) = west.simulate(&team);
-    let (east_winner, east_data) = east.simulate(&team);
-    let finals = Round::new("Finals".into(), west_winner, east_winner);
-    let (winner, finals_data) = finals.simulate(&team);
-    display::display_ladder(&west_data, &east_data, &finals_data, &winner.name)?;
-    Ok(())
+    let ((_, west_winner), west) = west.simulate(config, print, rng);
+    let ((_, east_winner), east) = east.simulate(config, print, rng);
+    let west_first = standings.order_finals(&[&west_winner, &east_winner], rng)[0] == 0;
+    let (west_seed, east_seed) = if west_first { (1, 2) } else { (2, 1) };
+    let format = &config.playoff_format.finals;
+    let finals =
+        Round::new("Finals".into(), format, (west_seed, west_winner), (east_seed, east_winner));
+    let ((_, winner), finals) = finals.simulate(config, print, rng);
+    PostseasonData { west, east, finals, winner: winner.name }
+}
+
+pub struct PostseasonData {
+    pub west: ConferenceData,
+    pub east: ConferenceData,
+    pub finals: RoundData,
+    pub winner: String,
 }
 
 struct ConferenceBracket {
@@ -94,7 +263,9 @@ struct ConferenceBracket {
 impl ConferenceBracket {
     fn new(name: String, teams: Vec<Team>) -> Self { ConferenceBracket { name, teams } }
 
-    fn simulate_play_in(&mut self, print: &str) -> [GameData; 3] {
+    fn simulate_play_in(
+        &mut self, config: &Config, print: &str, rng: &mut StdRng,
+    ) -> [GameData; 3] {
         self.teams.truncate(10);
         let tenth = self.teams.pop().unwrap();
         let ninth = self.teams.pop().unwrap();
@@ -102,20 +273,23 @@ impl ConferenceBracket {
         let seventh = self.teams.pop().unwrap();
         let mut name = format!("{} play-in round 1", self.name);
         let (seventh, round_3_home, round_1_data) =
-            simulate_with_info(&name, seventh, eighth, print);
+            simulate_with_info(&name, seventh, eighth, config, print, rng);
         name.pop();
         name.push('2');
-        let (round_3_away, _, round_2_data) = simulate_with_info(&name, ninth, tenth, print);
+        let (round_3_away, _, round_2_data) =
+            simulate_with_info(&name, ninth, tenth, config, print, rng);
         name.pop();
         name.push('3');
         let (eighth, _, round_3_data) =
-            simulate_with_info(&name, round_3_home, round_3_away, print);
+            simulate_with_info(&name, round_3_home, round_3_away, config, print, rng);
         self.teams.push(seventh);
         self.teams.push(eighth);
         [round_1_data, round_2_data, round_3_data]
     }
 
-    fn simulate(mut self, print: &str) -> (Team, ConferenceData) {
+    fn simulate(
+        mut self, config: &Config, print: &str, rng: &mut StdRng,
+    ) -> ((usize, Team), ConferenceData) {
         let mut names = vec![
             format!("{} round 1 (1 vs 8)", self.name),
             format!("{} round 1 (2 vs 7)", self.name),
@@ -125,20 +299,22 @@ impl ConferenceBracket {
             format!("{} semifinals (2/7 vs 3/6)", self.name),
             format!("{} finals (1 vs 8)", self.name),
         ];
-        let play_in_data = self.simulate_play_in(print);
+        let play_in_data = self.simulate_play_in(config, print, rng);
+        let mut teams: Vec<_> = (1..).zip(self.teams).collect();
+        let format = &config.playoff_format;
         let mut data = Vec::new();
-        for _ in 0..3 {
+        for format in [&format.round_1, &format.semifinals, &format.conference_finals] {
             let mut winners = Vec::new();
-            while !self.teams.is_empty() {
-                let round =
-                    Round::new(names.remove(0), self.teams.remove(0), self.teams.pop().unwrap());
-                let (winner, round_data) = round.simulate(print);
+            while !teams.is_empty() {
+                let (name, team_a) = (names.remove(0), teams.remove(0));
+                let round = Round::new(name, format, team_a, teams.pop().unwrap());
+                let (winner, round_data) = round.simulate(config, print, rng);
                 winners.push(winner);
                 data.push(round_data);
             }
-            self.teams = winners;
+            teams = winners;
         }
-        let winner = self.teams.remove(0);
+        let winner = teams.remove(0);
         let round_1_data: [RoundData; 4] = array::from_fn(|_| data.remove(0));
         let semis_data: [RoundData; 2] = array::from_fn(|_| data.remove(0));
         let finals_data = data.remove(0);
@@ -177,46 +353,72 @@ impl GameData {
 
 struct Round {
     name: String,
+    format: SeriesFormat,
     team_a: Team,
     team_b: Team,
+    team_a_seed: usize,
+    team_b_seed: usize,
     team_a_wins: u32,
     team_b_wins: u32,
+    team_a_home: Vec<bool>,
 }
 
 impl Round {
-    fn new(name: String, team_a: Team, team_b: Team) -> Self {
-        Round { name: name + " game ", team_a, team_b, team_a_wins: 0, team_b_wins: 0 }
+    fn new(
+        name: String, format: &SeriesFormat, team_a: (usize, Team), team_b: (usize, Team),
+    ) -> Self {
+        Round {
+            name: name + " game ",
+            format: format.clone(),
+            team_a: team_a.1,
+            team_b: team_b.1,
+            team_a_seed: team_a.0,
+            team_b_seed: team_b.0,
+            team_a_wins: 0,
+            team_b_wins: 0,
+            team_a_home: Vec::new(),
+        }
     }
 
-    fn play_game(&mut self, switch: bool, print: &str) -> bool {
-        let result = if switch {
-            simulate_from_teams(&self.name, &mut self.team_b, &mut self.team_a, false, print)
+    fn play_game(&mut self, switch: bool, config: &Config, print: &str, rng: &mut StdRng) -> bool {
+        let (home, away) = if switch {
+            (&mut self.team_b, &mut self.team_a)
         } else {
-            simulate_from_teams(&self.name, &mut self.team_a, &mut self.team_b, false, print)
+            (&mut self.team_a, &mut self.team_b)
         };
+        let (score, _) = simulate_from_teams(&self.name, home, away, None, config, print, rng);
+        let result = score.home_win();
+        self.team_a_home.push(!switch);
         if switch ^ result {
             self.team_a_wins += 1;
-            self.team_a_wins == 4
+            self.team_a_wins == self.format.wins_needed()
         } else {
             self.team_b_wins += 1;
-            self.team_b_wins == 4
+            self.team_b_wins == self.format.wins_needed()
         }
     }
 
-    fn simulate(mut self, print: &str) -> (Team, RoundData) {
+    fn simulate(
+        mut self, config: &Config, print: &str, rng: &mut StdRng,
+    ) -> ((usize, Team), RoundData) {
         let mut game = 1;
-        let team_b_advantage = self.team_a.season_wins < self.team_b.season_wins;
+        let team_b_advantage = self.team_b_seed < self.team_a_seed;
+        let name_len = self.name.len();
         loop {
-            self.name.push(char::from_digit(game, 10).unwrap());
-            let switch = team_b_advantage ^ matches!(game, 3 | 4 | 6);
-            if self.play_game(switch, print) {
+            self.name.push_str(&game.to_string());
+            let switch = team_b_advantage ^ !self.format.higher_seed_home(game);
+            if self.play_game(switch, config, print, rng) {
                 break;
             }
-            self.name.pop();
+            self.name.truncate(name_len);
             game += 1;
         }
         let data = RoundData::new(&self);
-        let winner = if self.team_a_wins == 4 { self.team_a } else { self.team_b };
+        let winner = if self.team_a_wins == self.format.wins_needed() {
+            (self.team_a_seed, self.team_a)
+        } else {
+            (self.team_b_seed, self.team_b)
+        };
         (winner, data)
     }
 }
@@ -225,8 +427,11 @@ impl Round {
 pub struct RoundData {
     pub team_a: String,
     pub team_b: String,
+    pub team_a_seed: usize,
+    pub team_b_seed: usize,
     pub team_a_wins: u32,
     pub team_b_wins: u32,
+    pub team_a_home: Vec<bool>,
 }
 
 impl RoundData {
@@ -234,8 +439,55 @@ impl RoundData {
         RoundData {
             team_a: round.team_a.name.clone(),
             team_b: round.team_b.name.clone(),
+            team_a_seed: round.team_a_seed,
+            team_b_seed: round.team_b_seed,
             team_a_wins: round.team_a_wins,
             team_b_wins: round.team_b_wins,
+            team_a_home: round.team_a_home.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::test_utils::team;
+
+    #[test]
+    fn simulated_scores_go_to_overtime_only_after_a_tie() {
+        let (config, home, away) = (Config::default(), team("BOS"), team("MIA"));
+        let model = ScoreModel::new(&config, &home, &away, 0.7);
+        let mut rng = StdRng::seed_from_u64(0);
+        let (mut home_wins, mut overtimes) = (0, 0);
+        for _ in 0..20_000 {
+            let regulation = model.period(1.0, &mut rng.clone());
+            let score = simulate_score(&config, &home, &away, 0.7, &mut rng);
+            assert_ne!(score.home, score.away);
+            if score.periods > 4 {
+                overtimes += 1;
+                assert_eq!(regulation.0, regulation.1);
+            } else {
+                assert_eq!(regulation, (score.home.into(), score.away.into()));
+            }
+            home_wins += u32::from(score.home_win());
+        }
+        assert!(overtimes > 0);
+        let rate = f64::from(home_wins) / 20_000.0;
+        assert!((rate - 0.7).abs() < 0.02, "{rate}");
+    }
+
+    #[test]
+    fn lower_seed_listed_first_plays_away_from_home() {
+        // after an upset the lower seed can be team a, home court still follows the seeds
+        let format = SeriesFormat::try_from(String::from("2-2-1-1-1")).unwrap();
+        let round = Round::new("Semifinals".into(), &format, (8, team("MIA")), (4, team("NYK")));
+        let config = Config::default();
+        let (_, data) = round.simulate(&config, "", &mut StdRng::seed_from_u64(0));
+        assert_eq!(data.team_a_home[..4], [false, false, true, true]);
+        for (game, &team_a_home) in (1..).zip(&data.team_a_home) {
+            assert_eq!(team_a_home, !format.higher_seed_home(game), "game {game}");
         }
     }
 }
@@ -0,0 +1,302 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::types::{Conference, Division, Record, Team};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tiebreaker {
+    HeadToHead,
+    DivisionLeader,
+    DivisionRecord,
+    ConferenceRecord,
+    EligibleOwnConference,
+    EligibleOtherConference,
+    OtherConferenceRecord,
+    PointDifferential,
+}
+
+const TWO_TEAMS: [Tiebreaker; 7] = [
+    Tiebreaker::HeadToHead,
+    Tiebreaker::DivisionLeader,
+    Tiebreaker::DivisionRecord,
+    Tiebreaker::ConferenceRecord,
+    Tiebreaker::EligibleOwnConference,
+    Tiebreaker::EligibleOtherConference,
+    Tiebreaker::PointDifferential,
+];
+
+const MORE_TEAMS: [Tiebreaker; 6] = [
+    Tiebreaker::DivisionLeader,
+    Tiebreaker::HeadToHead,
+    Tiebreaker::DivisionRecord,
+    Tiebreaker::ConferenceRecord,
+    Tiebreaker::EligibleOwnConference,
+    Tiebreaker::PointDifferential,
+];
+
+const FINALS: [Tiebreaker; 2] = [Tiebreaker::HeadToHead, Tiebreaker::OtherConferenceRecord];
+
+fn season_record(team: &Team) -> Record {
+    Record { wins: team.season_wins, loses: team.season_loses }
+}
+
+fn split_ties<F>(sorted: Vec<usize>, mut compare: F) -> Vec<Vec<usize>>
+where F: FnMut(usize, usize) -> Ordering {
+    let mut result: Vec<Vec<usize>> = Vec::new();
+    for i in sorted {
+        match result.last_mut() {
+            Some(last) if compare(last[0], i).is_eq() => last.push(i),
+            _ => result.push(vec![i]),
+        }
+    }
+    result
+}
+
+pub struct Standings {
+    teams: HashMap<String, (Conference, Division)>,
+    eligible: HashSet<String>,
+    leaders: HashSet<String>,
+}
+
+impl Standings {
+    #[must_use]
+    pub fn new(teams: &[&Team], rng: &mut StdRng) -> Self {
+        let mut eligible = HashSet::new();
+        for conference in [Conference::West, Conference::East] {
+            let mut percentages: Vec<_> = teams
+                .iter()
+                .filter(|x| x.conference == conference)
+                .map(|x| season_record(x).percentage())
+                .collect();
+            percentages.sort_by(|x, y| y.total_cmp(x));
+            let Some(&cutoff) = percentages.get(9).or(percentages.last()) else {
+                continue;
+            };
+            eligible.extend(
+                teams
+                    .iter()
+                    .filter(|x| x.conference == conference)
+                    .filter(|x| season_record(x).percentage() >= cutoff)
+                    .map(|x| x.name.clone()),
+            );
+        }
+        let mut standings = Standings {
+            teams: teams.iter().map(|x| (x.name.clone(), (x.conference, x.division))).collect(),
+            eligible,
+            leaders: HashSet::new(),
+        };
+        let mut divisions = Vec::new();
+        for team in teams {
+            if !divisions.contains(&team.division) {
+                divisions.push(team.division);
+            }
+        }
+        for division in divisions {
+            let members = (0..teams.len()).filter(|&i| teams[i].division == division);
+            let order = standings.order_group(teams, members.collect(), false, rng);
+            let leader = teams[order[0]].name.clone();
+            standings.leaders.insert(leader);
+        }
+        standings
+    }
+
+    #[must_use]
+    pub fn rank(&self, teams: Vec<Team>, rng: &mut StdRng) -> Vec<Team> {
+        let order = self.order(&teams.iter().collect::<Vec<_>>(), rng);
+        let mut teams: Vec<_> = teams.into_iter().map(Some).collect();
+        order.into_iter().filter_map(|i| teams[i].take()).collect()
+    }
+
+    #[must_use]
+    pub fn order(&self, teams: &[&Team], rng: &mut StdRng) -> Vec<usize> {
+        self.order_group(teams, (0..teams.len()).collect(), false, rng)
+    }
+
+    /// Orders the Finals teams, which break ties by head-to-head and then by record against the
+    /// other conference.
+    #[must_use]
+    pub fn order_finals(&self, teams: &[&Team], rng: &mut StdRng) -> Vec<usize> {
+        self.order_group(teams, (0..teams.len()).collect(), true, rng)
+    }
+
+    fn order_group(
+        &self, teams: &[&Team], mut group: Vec<usize>, finals: bool, rng: &mut StdRng,
+    ) -> Vec<usize> {
+        let compare = |x: usize, y: usize| {
+            season_record(teams[y]).percentage().total_cmp(&season_record(teams[x]).percentage())
+        };
+        group.sort_by(|&x, &y| compare(x, y));
+        split_ties(group, compare)
+            .into_iter()
+            .flat_map(|x| self.break_tie(teams, x, finals, rng))
+            .collect()
+    }
+
+    fn break_tie(
+        &self, teams: &[&Team], mut group: Vec<usize>, finals: bool, rng: &mut StdRng,
+    ) -> Vec<usize> {
+        if group.len() < 2 {
+            return group;
+        }
+        let tiebreakers: &[Tiebreaker] = if finals {
+            &FINALS
+        } else if group.len() == 2 {
+            &TWO_TEAMS
+        } else {
+            &MORE_TEAMS
+        };
+        let same_division = group.iter().all(|&i| teams[i].division == teams[group[0]].division);
+        let names: HashSet<&str> = group.iter().map(|&i| teams[i].name.as_str()).collect();
+        for &tiebreaker in tiebreakers {
+            if tiebreaker == Tiebreaker::DivisionRecord && !same_division {
+                continue;
+            }
+            let compare = |x: usize, y: usize| self.compare(tiebreaker, teams[y], teams[x], &names);
+            group.sort_by(|&x, &y| compare(x, y));
+            if compare(group[0], group[group.len() - 1]).is_ne() {
+                return split_ties(group, compare)
+                    .into_iter()
+                    .flat_map(|x| self.break_tie(teams, x, finals, rng))
+                    .collect();
+            }
+        }
+        group.shuffle(rng);
+        group
+    }
+
+    fn compare(
+        &self, tiebreaker: Tiebreaker, a: &Team, b: &Team, group: &HashSet<&str>,
+    ) -> Ordering {
+        match tiebreaker {
+            Tiebreaker::DivisionLeader => {
+                self.leaders.contains(&a.name).cmp(&self.leaders.contains(&b.name))
+            },
+            Tiebreaker::PointDifferential => a.point_differential.cmp(&b.point_differential),
+            _ => {
+                let a = self.record(tiebreaker, a, group).percentage();
+                let b = self.record(tiebreaker, b, group).percentage();
+                a.total_cmp(&b)
+            },
+        }
+    }
+
+    fn record(&self, tiebreaker: Tiebreaker, team: &Team, group: &HashSet<&str>) -> Record {
+        let mut result = Record::default();
+        for (opponent, record) in &team.head_to_head {
+            let Some(&(conference, division)) = self.teams.get(opponent) else {
+                continue;
+            };
+            let eligible = self.eligible.contains(opponent);
+            let counts = match tiebreaker {
+                Tiebreaker::HeadToHead => group.contains(opponent.as_str()),
+                Tiebreaker::DivisionRecord => division == team.division,
+                Tiebreaker::ConferenceRecord => conference == team.conference,
+                Tiebreaker::EligibleOwnConference => eligible && conference == team.conference,
+                Tiebreaker::EligibleOtherConference => eligible && conference != team.conference,
+                Tiebreaker::OtherConferenceRecord => conference != team.conference,
+                Tiebreaker::DivisionLeader | Tiebreaker::PointDifferential => false,
+            };
+            if counts {
+                result.add(*record);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::test_utils;
+
+    fn team(name: &str, division: Division, wins: u32, loses: u32) -> Team {
+        let conference = match division {
+            Division::Atlantic | Division::Central | Division::Southeast => Conference::East,
+            Division::Northwest | Division::Pacific | Division::Southwest => Conference::West,
+        };
+        let team = test_utils::team(name);
+        Team { conference, division, season_wins: wins, season_loses: loses, ..team }
+    }
+
+    fn play(teams: &mut [Team], winner: usize, loser: usize, games: u32) {
+        let (winner_name, loser_name) = (teams[winner].name.clone(), teams[loser].name.clone());
+        teams[winner].head_to_head.entry(loser_name).or_default().wins += games;
+        teams[loser].head_to_head.entry(winner_name).or_default().loses += games;
+    }
+
+    fn order(teams: &[Team]) -> Vec<&str> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let teams: Vec<_> = teams.iter().collect();
+        let standings = Standings::new(&teams, &mut rng);
+        standings.order(&teams, &mut rng).into_iter().map(|i| teams[i].name.as_str()).collect()
+    }
+
+    #[test]
+    fn two_team_tie_is_broken_by_head_to_head() {
+        let mut teams = vec![
+            team("BOS", Division::Atlantic, 41, 41),
+            team("MIA", Division::Southeast, 41, 41),
+            team("CHA", Division::Southeast, 20, 62),
+        ];
+        play(&mut teams, 1, 0, 3);
+        play(&mut teams, 0, 1, 1);
+        play(&mut teams, 0, 2, 4);
+        play(&mut teams, 2, 1, 4);
+        assert_eq!(order(&teams), ["MIA", "BOS", "CHA"]);
+    }
+
+    #[test]
+    fn three_team_tie_is_broken_by_division_leader() {
+        let mut teams = vec![
+            team("CHI", Division::Central, 45, 37),
+            team("MIL", Division::Central, 50, 32),
+            team("DET", Division::Central, 45, 37),
+            team("NYK", Division::Atlantic, 45, 37),
+        ];
+        play(&mut teams, 0, 2, 2);
+        play(&mut teams, 2, 0, 2);
+        play(&mut teams, 0, 3, 2);
+        play(&mut teams, 3, 0, 2);
+        play(&mut teams, 2, 3, 3);
+        play(&mut teams, 3, 2, 1);
+        assert_eq!(order(&teams), ["MIL", "NYK", "DET", "CHI"]);
+    }
+
+    #[test]
+    fn two_team_tie_falls_back_to_point_differential() {
+        let mut teams =
+            vec![team("PHX", Division::Pacific, 48, 34), team("DEN", Division::Northwest, 48, 34)];
+        play(&mut teams, 0, 1, 2);
+        play(&mut teams, 1, 0, 2);
+        teams[0].point_differential = -10;
+        teams[1].point_differential = 50;
+        assert_eq!(order(&teams), ["DEN", "PHX"]);
+    }
+
+    #[test]
+    fn finals_tie_is_broken_by_record_against_other_conference() {
+        let mut teams = vec![
+            team("DEN", Division::Northwest, 50, 32),
+            team("UTA", Division::Northwest, 30, 52),
+            team("BOS", Division::Atlantic, 50, 32),
+            team("NYK", Division::Atlantic, 55, 27),
+        ];
+        play(&mut teams, 0, 2, 1);
+        play(&mut teams, 2, 0, 1);
+        play(&mut teams, 0, 1, 4);
+        play(&mut teams, 3, 2, 4);
+        play(&mut teams, 2, 1, 2);
+        let mut rng = StdRng::seed_from_u64(0);
+        let all: Vec<_> = teams.iter().collect();
+        let standings = Standings::new(&all, &mut rng);
+        let finalists = [&teams[0], &teams[2]];
+        // DEN leads its division and has the better conference record, BOS beat the West
+        assert_eq!(standings.order(&finalists, &mut rng), [0, 1]);
+        assert_eq!(standings.order_finals(&finalists, &mut rng), [1, 0]);
+    }
+}
@@ -0,0 +1,38 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+
+use crate::types::{Conference, Division, Game, Team};
+
+pub fn team(name: &str) -> Team {
+    Team {
+        id: None,
+        name: name.into(),
+        full_name: name.into(),
+        conference: Conference::East,
+        division: Division::Atlantic,
+        elo: 1000.0,
+        season_wins: 0,
+        season_loses: 0,
+        head_to_head: BTreeMap::new(),
+        point_differential: 0,
+        glicko: None,
+        points: None,
+    }
+}
+
+pub fn game(home: usize, away: usize, home_score: u32, away_score: u32) -> Game {
+    Game {
+        id: 0,
+        date: Utc::now(),
+        season: 2023,
+        home_team_idx: home,
+        away_team_idx: away,
+        home_score,
+        away_score,
+        neutral: false,
+        status: "Final".into(),
+        period: 4,
+        postseason: false,
+    }
+}
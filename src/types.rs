@@ -1,17 +1,81 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug)]
 pub struct Game {
+    pub id: u32,
     pub date: DateTime<Utc>,
+    pub season: u32,
     pub home_team_idx: usize,
     pub away_team_idx: usize,
     pub home_score: u32,
     pub away_score: u32,
+    pub neutral: bool,
+    pub status: String,
+    pub period: u32,
+    pub postseason: bool,
+}
+
+impl Game {
+    #[must_use]
+    pub fn is_final(&self) -> bool {
+        self.status == "Final" || (self.status.is_empty() && self.home_score + self.away_score > 0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatingChange {
+    pub date: DateTime<Utc>,
+    pub season: u32,
+    pub game_id: u32,
+    pub home: String,
+    pub away: String,
+    pub home_score: u32,
+    pub away_score: u32,
+    pub home_win: bool,
+    pub simulated: bool,
+    pub expected: f64,
+    pub home_before: f64,
+    pub home_after: f64,
+    pub away_before: f64,
+    pub away_after: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl RatingChange {
+    #[must_use]
+    pub fn new(
+        game: &Game, home: &Team, away: &Team, home_win: bool, expected: f64, home_before: f64,
+        away_before: f64,
+    ) -> Self {
+        RatingChange {
+            date: game.date,
+            season: game.season,
+            game_id: game.id,
+            home: home.name.clone(),
+            away: away.name.clone(),
+            home_score: game.home_score,
+            away_score: game.away_score,
+            home_win,
+            simulated: !game.is_final(),
+            expected,
+            home_before,
+            home_after: home.elo,
+            away_before,
+            away_after: away.elo,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TeamId(pub u32);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Team {
+    #[serde(default)]
+    pub id: Option<TeamId>,
     pub name: String,
     pub full_name: String,
     pub conference: Conference,
@@ -19,15 +83,63 @@ pub struct Team {
     pub elo: f64,
     pub season_wins: u32,
     pub season_loses: u32,
+    #[serde(default)]
+    pub head_to_head: BTreeMap<String, Record>,
+    #[serde(default)]
+    pub point_differential: i64,
+    #[serde(default)]
+    pub glicko: Option<GlickoState>,
+    #[serde(default)]
+    pub points: Option<PointRatings>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PointRatings {
+    pub srs: f64,
+    pub pythagorean: f64,
+    #[serde(default)]
+    pub offense: f64,
+    #[serde(default)]
+    pub defense: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GlickoState {
+    pub rd: f64,
+    pub volatility: f64,
+    pub last_played: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Record {
+    pub wins: u32,
+    pub loses: u32,
+}
+
+impl Record {
+    #[must_use]
+    pub fn percentage(self) -> f64 {
+        let games = self.wins + self.loses;
+        if games == 0 {
+            0.5
+        } else {
+            f64::from(self.wins) / f64::from(games)
+        }
+    }
+
+    pub fn add(&mut self, other: Record) {
+        self.wins += other.wins;
+        self.loses += other.loses;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Conference {
     West,
     East,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Division {
     Atlantic,
     Central,
@@ -1,22 +1,75 @@
 use std::cell::RefCell;
+use std::f64::consts::SQRT_2;
 use std::fs::File;
 use std::io::BufReader;
 use std::{fs, io};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{random, SeedableRng};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::registry::{GameRecord, TeamRegistry};
 use crate::types::{Game, Team};
 
-pub fn write_json<T>(data: &Vec<T>, path: &str) -> Result<()>
-where T: Serialize {
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub teams: String,
+    pub previous_games: String,
+    pub config: String,
+    pub processed: String,
+    pub processed_at: String,
+    pub games: String,
+    pub after_season: String,
+    pub montecarlo: String,
+    pub backtest: String,
+    pub cache: String,
+    pub history: String,
+    pub season_history: String,
+}
+
+impl Paths {
+    #[must_use]
+    pub fn new(dir: &str) -> Self {
+        Paths {
+            teams: format!("{dir}/teams.json"),
+            previous_games: format!("{dir}/prev_games.json"),
+            config: format!("{dir}/config.json"),
+            processed: format!("{dir}/processed.json"),
+            processed_at: format!("{dir}/processed_at.json"),
+            games: format!("{dir}/games.json"),
+            after_season: format!("{dir}/after_season.json"),
+            montecarlo: format!("{dir}/montecarlo.json"),
+            backtest: format!("{dir}/backtest.json"),
+            cache: format!("{dir}/cache"),
+            history: format!("{dir}/history.json"),
+            season_history: format!("{dir}/season_history.json"),
+        }
+    }
+
+    /// The seed is kept next to the standings it ranks, so moving one with `--output` or
+    /// `--input` moves the other.
+    #[must_use]
+    pub fn after_season_seed(&self) -> String {
+        let stem = self.after_season.strip_suffix(".json").unwrap_or(&self.after_season);
+        format!("{stem}_seed.json")
+    }
+}
+
+impl Default for Paths {
+    fn default() -> Self { Paths::new("files") }
+}
+
+pub fn write_json<T>(data: &T, path: &str) -> Result<()>
+where T: Serialize + ?Sized {
     let serialized = serde_json::to_string_pretty(data)?;
     fs::write(path, serialized)?;
     Ok(())
 }
 
-fn read_json<T>(path: &str) -> Result<Vec<T>>
+pub fn read_json<T>(path: &str) -> Result<T>
 where T: DeserializeOwned {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
@@ -24,12 +77,27 @@ where T: DeserializeOwned {
     Ok(result)
 }
 
-pub fn load_games(path: &str) -> Result<Vec<Game>> { read_json(path) }
+pub fn load_games(path: &str, teams: &[Team]) -> Result<Vec<Game>> {
+    let registry = TeamRegistry::new(teams)?;
+    let file = File::open(path).with_context(|| format!("can't read games from {path}"))?;
+    let records: Vec<GameRecord> = serde_json::from_reader(BufReader::new(file)).with_context(|| {
+        format!(
+            "can't parse games in {path}, download them again if they were saved by an older \
+             version"
+        )
+    })?;
+    records.into_iter().map(|x| registry.game(x)).collect()
+}
+
+pub fn write_games(games: &[Game], teams: &[Team], path: &str) -> Result<()> {
+    let records = games.iter().map(|x| GameRecord::new(x, teams)).collect::<Result<Vec<_>>>()?;
+    write_json(&records, path)
+}
 
 pub fn load_teams(path: &str) -> Result<Vec<Team>> { read_json(path) }
 
 pub fn load_teams_cell(path: &str) -> Result<Vec<RefCell<Team>>> {
-    Ok(read_json(path)?.into_iter().map(RefCell::new).collect())
+    Ok(load_teams(path)?.into_iter().map(RefCell::new).collect())
 }
 
 pub fn ask_for_team(teams: &[Team]) -> String {
@@ -55,8 +123,40 @@ where F: FnMut(&str) -> bool {
     }
 }
 
+#[must_use]
+pub fn days(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    f64::from(i32::try_from((to - from).num_days()).unwrap_or(i32::MAX))
+}
+
+#[must_use]
+pub fn normal_cdf(x: f64) -> f64 {
+    let z = x.abs() / SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * z);
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - polynomial * (-z * z).exp();
+    if x < 0.0 {
+        0.5 * (1.0 - erf)
+    } else {
+        0.5 * (1.0 + erf)
+    }
+}
+
 pub fn expected(a: f64, b: f64) -> f64 { 1.0 / (1.0 + 10f64.powf((b - a) / 400.0)) }
 
+#[must_use]
+pub fn margin_multiplier(margin: u32, winner_elo_diff: f64) -> f64 {
+    (f64::from(margin) + 3.0).powf(0.8) / (7.5 + 0.006 * winner_elo_diff)
+}
+
+pub fn seeded_rng(seed: Option<u64>) -> StdRng {
+    let seed = seed.unwrap_or_else(random);
+    println!("Seed: {seed}");
+    StdRng::seed_from_u64(seed)
+}
+
 pub fn ask(question: &str) -> bool {
     println!("{question} (y/n)");
     let mut buffer = String::new();
@@ -72,3 +172,19 @@ pub fn ask(question: &str) -> bool {
         }
     }
 }
+
+#[must_use]
+pub fn ask_number(question: &str) -> u32 {
+    println!("{question}");
+    let mut buffer = String::new();
+    loop {
+        io::stdin().read_line(&mut buffer).unwrap();
+        match buffer.trim().parse() {
+            Ok(result) if result > 0 => return result,
+            _ => {
+                println!("Try again");
+                buffer.clear();
+            },
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::{env, fs};
+
+use nba::get_data::{get_previous_games, get_season_games, get_teams};
+use nba::montecarlo::{simulate_many, MonteCarloResult};
+use nba::process_data::{process_games, update_ratings, ProcessedAt};
+use nba::provider::Fixtures;
+use nba::simulate::simulate_season;
+use nba::types::Conference;
+use nba::utils::{self, Paths};
+
+#[tokio::test]
+async fn fetch_process_and_simulate_from_fixtures() {
+    let dir = env::temp_dir().join(format!("nba-pipeline-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let paths = Paths::new(dir.to_str().unwrap());
+    let provider = Fixtures::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures").into());
+
+    get_teams(&provider, &paths).await.unwrap();
+    get_previous_games(&provider, &paths).await.unwrap();
+    get_season_games(&provider, &paths).await.unwrap();
+    process_games(&paths, Some("")).unwrap();
+    let processed_at: ProcessedAt = utils::read_json(&paths.processed_at).unwrap();
+    update_ratings(&paths, Some("")).unwrap();
+    let teams = utils::load_teams(&paths.teams).unwrap();
+    let current = utils::load_games(&paths.games, &teams).unwrap();
+    let updated_at: ProcessedAt = utils::read_json(&paths.processed_at).unwrap();
+    assert_eq!(
+        updated_at.applied.len(),
+        processed_at.applied.len() + current.iter().filter(|x| x.is_final()).count()
+    );
+    let updated = utils::load_teams(&paths.processed).unwrap();
+    update_ratings(&paths, Some("")).unwrap();
+    let unchanged = utils::load_teams(&paths.processed).unwrap();
+    assert!(updated.iter().zip(&unchanged).all(|(x, y)| (x.elo - y.elo).abs() < 1e-9));
+    simulate_season(&paths, Some(""), Some(1)).unwrap();
+
+    let teams = utils::load_teams(&paths.after_season).unwrap();
+    let games = utils::load_games(&paths.games, &teams).unwrap();
+    let mut played: HashMap<&str, u32> = HashMap::new();
+    for game in games.iter().filter(|x| !x.postseason) {
+        *played.entry(&teams[game.home_team_idx].name).or_default() += 1;
+        *played.entry(&teams[game.away_team_idx].name).or_default() += 1;
+    }
+    assert_eq!(teams.len(), 30);
+    for team in &teams {
+        assert_eq!(
+            team.season_wins + team.season_loses,
+            played[team.name.as_str()],
+            "{}",
+            team.name
+        );
+    }
+    let processed = utils::load_teams(&paths.processed).unwrap();
+    assert!(processed.iter().any(|x| x.elo != processed[0].elo));
+
+    simulate_many(&paths, 20, Some(1)).unwrap();
+    let result: MonteCarloResult = utils::read_json(&paths.montecarlo).unwrap();
+    let close = |x: f64| (x - 1.0).abs() < 1e-9;
+    for conference in [Conference::West, Conference::East] {
+        let odds: Vec<_> = result.teams.iter().filter(|x| x.conference == conference).collect();
+        for seed in 0..odds.len() {
+            let total: f64 = odds.iter().filter_map(|x| x.seeds.get(seed)).sum();
+            assert!(close(total), "{conference:?} seed {}: {total}", seed + 1);
+        }
+    }
+    assert!(close(result.teams.iter().map(|x| x.champion).sum()));
+    for odds in &result.teams {
+        assert!(close(odds.wins.iter().sum()), "{}", odds.name);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}